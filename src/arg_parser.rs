@@ -1,15 +1,56 @@
-use crate::{diagnostics::print_help, task::TaskDef, Error};
+use crate::{
+    color::set_color_when,
+    control::ControlMessage,
+    diagnostics::print_help,
+    dir_config::{interpolate_env, read_dir_config},
+    renderer::SummarySort,
+    task::{LogLevel, RetryBackoff, TaskDef},
+    theme::Theme,
+    Error,
+};
 use crossterm::style::Color;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use globset::{Glob, GlobSetBuilder};
+use regex::Regex;
 use std::{
     env::{self, Args},
     iter::Peekable,
     path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
-pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef, Error> {
+/// Where tasks without a name given with `-n` or in a `.congregation` file get one from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NameFrom {
+    /// The name of the working directory, or the task's number and command without `-d`.
+    Dir,
+    Command,
+    /// Just the task's number, like `#3`.
+    Index,
+}
+
+impl FromStr for NameFrom {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dir" => Ok(Self::Dir),
+            "command" => Ok(Self::Command),
+            "index" => Ok(Self::Index),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn parse_task<I: Iterator<Item = String>>(
+    args: &mut Peekable<I>,
+    task_count: i32,
+    name_from: NameFrom,
+) -> Result<TaskDef, Error> {
     let error_title = || format!("invalid syntax (in task {})", task_count + 1);
 
-    if !args.next().is_some_and(|arg| arg == "run") {
+    if args.next().is_none_or(|arg| arg != "run") {
         return Err(Error {
             title: error_title(),
             message: "expected 'run'".into(),
@@ -21,8 +62,23 @@ pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef,
     let mut workdir = None;
     let mut color = None;
     let mut restart_delay_secs = None;
+    let mut retry_backoff = RetryBackoff::Fixed;
+    let mut retry_on = None;
+    let mut crashloop_threshold = None;
+    let mut success_codes = Vec::new();
+    let mut nice = None;
+    let mut order = 0;
+    let mut group = None;
+    let mut watch = None::<GlobSetBuilder>;
+    let mut input = None;
+    let mut env_passthrough = None;
+    let mut env = Vec::new();
+    let mut login = false;
+    let mut health_url = None;
+    let mut ready_timeout = None;
+    let mut encoding = None;
 
-    let mut parse_flag = |args: &mut Peekable<Args>, flag: &str| {
+    let mut parse_flag = |args: &mut Peekable<I>, flag: &str| {
         match flag {
             "-n" => {
                 name = Some(match args.next() {
@@ -61,28 +117,16 @@ pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef,
                     });
                 };
 
-                let invalid_color = Error {
-                    title: error_title(),
-                    message: format!("invalid color '{color_arg}'"),
-                    notes: vec!["color syntax: RRGGBB (hex)".into()],
-                    ..Error::default()
-                };
-
-                if color_arg.len() != 6 {
-                    return Err(invalid_color);
-                }
-
-                let Ok(r) = u8::from_str_radix(&color_arg[0..2], 16) else {
-                    return Err(invalid_color);
-                };
-                let Ok(g) = u8::from_str_radix(&color_arg[2..4], 16) else {
-                    return Err(invalid_color);
-                };
-                let Ok(b) = u8::from_str_radix(&color_arg[4..6], 16) else {
-                    return Err(invalid_color);
+                let Some(parsed) = parse_color(&color_arg) else {
+                    return Err(Error {
+                        title: error_title(),
+                        message: format!("invalid color '{color_arg}'"),
+                        notes: vec!["color syntax: RRGGBB (hex)".into()],
+                        ..Error::default()
+                    });
                 };
 
-                color = Some(Color::Rgb { r, g, b });
+                color = Some(parsed);
             }
             "-r" => {
                 restart_delay_secs = Some(3);
@@ -94,6 +138,158 @@ pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef,
                     }
                 }
             }
+            "--retry-backoff" => {
+                retry_backoff = flag_value(args, flag, "'fixed' or 'exponential'", error_title())?
+            }
+            "--retry-on" => {
+                let pattern: String =
+                    flag_value(args, flag, "a regular expression", error_title())?;
+                retry_on = Some(Regex::new(&pattern).map_err(|err| Error {
+                    title: error_title(),
+                    message: format!("invalid regular expression '{pattern}' for {flag}"),
+                    notes: vec![err.to_string()],
+                    ..Error::default()
+                })?);
+            }
+            "--success-codes" => {
+                let list: String = flag_value(args, flag, "a list of exit codes", error_title())?;
+                for code in list.split(',') {
+                    success_codes.push(code.trim().parse().map_err(|_| Error {
+                        title: error_title(),
+                        message: format!("invalid exit code '{code}' for {flag}"),
+                        notes: vec!["exit codes are separated by commas, e.g. '1,2'".into()],
+                        ..Error::default()
+                    })?);
+                }
+            }
+            "--crashloop-threshold" => {
+                crashloop_threshold = Some(flag_value(
+                    args,
+                    flag,
+                    "a number of failures",
+                    error_title(),
+                )?)
+            }
+            "--nice" => nice = Some(flag_value(args, flag, "a niceness", error_title())?),
+            "--order" => order = flag_value(args, flag, "a number", error_title())?,
+            "--group" => {
+                group = Some(match args.next() {
+                    Some(group) => group,
+                    None => {
+                        return Err(Error {
+                            title: error_title(),
+                            message: "expected group name after --group".into(),
+                            ..Error::default()
+                        })
+                    }
+                });
+            }
+            "--watch" => {
+                let Some(pattern) = args.next() else {
+                    return Err(Error {
+                        title: error_title(),
+                        message: "expected glob pattern after --watch".into(),
+                        ..Error::default()
+                    });
+                };
+
+                let glob = Glob::new(&pattern).map_err(|err| Error {
+                    title: error_title(),
+                    message: format!("invalid glob pattern '{pattern}'"),
+                    notes: vec![err.kind().to_string()],
+                    ..Error::default()
+                })?;
+                watch.get_or_insert_with(GlobSetBuilder::new).add(glob);
+            }
+            "--env-passthrough" => {
+                let list: String =
+                    flag_value(args, flag, "a list of variable names", error_title())?;
+                env_passthrough = Some(
+                    list.split(',')
+                        .map(str::trim)
+                        .filter(|key| !key.is_empty())
+                        .map(str::to_owned)
+                        .collect(),
+                );
+            }
+            "-e" => {
+                let variable: String = flag_value(args, flag, "'<name>=<value>'", error_title())?;
+                let Some((key, value)) = variable.split_once('=') else {
+                    return Err(Error {
+                        title: error_title(),
+                        message: format!("invalid value '{variable}' for {flag}"),
+                        notes: vec![
+                            "variables are given as '<name>=<value>', e.g. 'PORT=3000'".into()
+                        ],
+                        ..Error::default()
+                    });
+                };
+                let value = interpolate_env(value, &env).map_err(|err| Error {
+                    title: error_title(),
+                    message: format!("invalid value '{variable}' for {flag}: {err}"),
+                    notes: vec!["a default can be given with ${VAR:-default}".into()],
+                    ..Error::default()
+                })?;
+                env.push((key.to_owned(), value));
+            }
+            "--env-file" => {
+                let path: String = flag_value(args, flag, "a file path", error_title())?;
+                read_env_file(&path, &mut env).map_err(|message| Error {
+                    title: error_title(),
+                    message,
+                    notes: vec!["a default can be given with ${VAR:-default}".into()],
+                    ..Error::default()
+                })?;
+            }
+            "--login" => login = true,
+            "--health-url" => {
+                let url: String = flag_value(args, flag, "a URL", error_title())?;
+                health_url = Some(url.parse().map_err(|err| Error {
+                    title: error_title(),
+                    message: format!("invalid URL '{url}' for {flag}: {err}"),
+                    notes: vec!["e.g. http://localhost:3000/health".into()],
+                    ..Error::default()
+                })?);
+            }
+            "--ready-timeout" => {
+                let value: String = flag_value(args, flag, "a duration", error_title())?;
+                ready_timeout = Some(parse_duration(&value).ok_or_else(|| Error {
+                    title: error_title(),
+                    message: format!("invalid value '{value}' for {flag}, expected a duration"),
+                    notes: vec![
+                        "durations are given in seconds, or with a unit like '90s' or '5m'".into(),
+                    ],
+                    ..Error::default()
+                })?);
+            }
+            "--encoding" => {
+                let label: String = flag_value(args, flag, "an encoding", error_title())?;
+                // output is split into lines at newline bytes, which UTF-16 doesn't have
+                let found = Encoding::for_label(label.as_bytes())
+                    .filter(|found| *found != UTF_16LE && *found != UTF_16BE);
+                let Some(found) = found else {
+                    return Err(Error {
+                        title: error_title(),
+                        message: format!("unknown encoding '{label}' for {flag}"),
+                        notes: vec!["e.g. 'windows-1252', 'shift_jis' or 'gbk'".into()],
+                        ..Error::default()
+                    });
+                };
+                // UTF-8 is read that way anyway
+                encoding = Some(found).filter(|found| *found != UTF_8);
+            }
+            "--input" => {
+                let text: String = flag_value(args, flag, "the input text", error_title())?;
+                input = Some(text.into_bytes());
+            }
+            "--input-file" => {
+                let path: String = flag_value(args, flag, "a file path", error_title())?;
+                input = Some(std::fs::read(&path).map_err(|err| Error {
+                    title: error_title(),
+                    message: format!("failed to read input from '{path}': {err}"),
+                    ..Error::default()
+                })?);
+            }
             _ => {
                 return Err(Error {
                     title: error_title(),
@@ -110,7 +306,7 @@ pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef,
         parse_flag(args, &flag)?;
     }
 
-    let Some(command) = args.next() else {
+    let Some(command_arg) = args.next() else {
         return Err(Error {
             title: error_title(),
             message: "expected command after 'run' keyword".into(),
@@ -118,23 +314,78 @@ pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef,
         });
     };
 
-    while args.peek().is_some_and(|arg| arg != "run") {
+    // `@<file>` reads the command from a file, which is handy for longer scripts
+    let mut command = match command_arg.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .map(|script| script.trim_end().to_owned())
+            .map_err(|err| Error {
+                title: error_title(),
+                message: format!("failed to read command from '{path}': {err}"),
+                ..Error::default()
+            })?,
+        None => command_arg.clone(),
+    };
+
+    while args.peek().is_some_and(|arg| !starts_task(arg)) {
         let arg = args.next().unwrap();
-        if arg.starts_with('-') {
+        if arg == "--" {
+            while let Some(arg) = args.next_if(|arg| !starts_task(arg)) {
+                command.push(' ');
+                command.push_str(&shell_quote(&arg));
+            }
+        } else if arg.starts_with('-') {
             parse_flag(args, &arg)?;
-        } else {
+        } else if command_arg.starts_with('@') {
             return Err(Error {
                 title: error_title(),
-                message: format!("expected '-n <name>', '-d <dir>', '-c <color>' or 'run' after command, got '{arg}'"),
+                message: format!("expected '-n <name>', '-d <dir>', '-c <color>', '--' or 'run' after command, got '{arg}'"),
                 notes: vec!["if your command includes spaces, please wrap it in quotes".into()],
                 ..Error::default()
             });
+        } else {
+            // most likely the words of an unquoted command, which continue up to the next flag
+            let mut words = vec![command_arg.as_str(), arg.as_str()];
+            let rest: Vec<String> = std::iter::from_fn(|| {
+                args.next_if(|arg| !arg.starts_with('-') && !starts_task(arg))
+            })
+            .collect();
+            words.extend(rest.iter().map(String::as_str));
+            let intended = words.join(" ");
+            return Err(Error {
+                title: error_title(),
+                message: format!("unexpected '{arg}' after command '{command_arg}'"),
+                notes: vec![
+                    "commands with spaces have to be wrapped in quotes, did you mean:".into(),
+                    format!("run {}", shell_quote(&intended)),
+                    format!(
+                        "or pass the rest as arguments: run {command_arg} -- {}",
+                        words[1..].join(" ")
+                    ),
+                ],
+                ..Error::default()
+            });
         }
     }
 
-    let name =
-        name.or_else(|| workdir.clone())
-            .unwrap_or(format!("#{}: {}", task_count + 1, &command,));
+    let workdir = workdir
+        .map(|workdir| interpolate_env(&workdir, &env))
+        .transpose()
+        .map_err(|err| Error {
+            title: error_title(),
+            message: format!("invalid working directory: {err}"),
+            notes: vec!["a default can be given with ${VAR:-default}".into()],
+            ..Error::default()
+        })?;
+
+    let name_given = name.is_some();
+    let has_workdir = workdir.is_some();
+    let index = format!("#{}", task_count + 1);
+    let mut name = name.unwrap_or_else(|| match (name_from, &workdir) {
+        (NameFrom::Dir, Some(workdir)) => workdir.clone(),
+        (NameFrom::Dir, None) => format!("{index}: {command_arg}"),
+        (NameFrom::Command, _) => command_arg.clone(),
+        (NameFrom::Index, _) => index,
+    });
 
     let workdir = workdir
         .map(PathBuf::from)
@@ -145,17 +396,500 @@ pub fn parse_task(args: &mut Peekable<Args>, task_count: i32) -> Result<TaskDef,
         message: format!("failed to resolve working directory: {err}"),
         ..Error::default()
     })?;
+    if !workdir.is_dir() {
+        return Err(Error {
+            title: format!("error in task '{name}'"),
+            message: format!(
+                "working directory '{}' is not a directory",
+                workdir.display()
+            ),
+            notes: vec!["-d expects the directory to run the task in".into()],
+            ..Error::default()
+        });
+    }
+
+    // the full path can be very long, its last part is usually enough to tell tasks apart
+    if has_workdir && !name_given && name_from == NameFrom::Dir {
+        if let Some(dir_name) = workdir.file_name() {
+            name = dir_name.to_string_lossy().into_owned();
+        }
+    }
+
+    if has_workdir {
+        let dir_config = read_dir_config(&workdir).map_err(|message| Error {
+            title: format!("error in task '{name}'"),
+            message,
+            ..Error::default()
+        })?;
+        if !name_given {
+            if let Some(dir_name) = dir_config.name {
+                name = dir_name;
+            }
+        }
+        color = color.or(dir_config.color);
+    }
+
+    if ready_timeout.is_some() && health_url.is_none() {
+        return Err(Error {
+            title: format!("error in task '{name}'"),
+            message: "--ready-timeout needs a --health-url to wait for".into(),
+            ..Error::default()
+        });
+    }
+
+    let watch = watch
+        .map(|builder| builder.build())
+        .transpose()
+        .map_err(|err| Error {
+            title: format!("error in task '{name}'"),
+            message: format!("invalid watch patterns: {err}"),
+            ..Error::default()
+        })?;
 
     Ok(TaskDef {
         name,
         command,
         workdir,
         color,
-        restart_delay_secs,
+        // retrying on matching output restarts the task after the default delay without -r
+        restart_delay_secs: restart_delay_secs.or(retry_on.as_ref().map(|_| 3)),
+        retry_backoff,
+        retry_on,
+        crashloop_threshold,
+        success_codes,
+        nice,
+        order,
+        watch,
+        group,
+        input,
+        env_passthrough,
+        env,
+        login,
+        health_url,
+        ready_timeout,
+        encoding,
+        start_delay: None,
     })
 }
 
-pub fn parse_args() -> Result<Vec<TaskDef>, Error> {
+/// Matches a level at the start of a line, optionally in brackets, like `INFO` or `[warn]`.
+const DEFAULT_LEVEL_PATTERN: &str = r"(?i)^\W*(trace|debug|info|warn|warning|error)\b";
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub separate_stderr: bool,
+    pub colorize_lines: bool,
+    pub follow_threshold: usize,
+    pub scroll_step: usize,
+    pub page_step: Option<usize>,
+    pub start_paused: bool,
+    pub plain: bool,
+    pub grep: Option<Regex>,
+    pub prefix_only_errors: bool,
+    pub prefix_format: String,
+    pub control: Option<PathBuf>,
+    pub write_status: Option<PathBuf>,
+    pub tab_width: usize,
+    pub label: Option<String>,
+    pub buffer_output: bool,
+    pub theme: Theme,
+    pub raw: bool,
+    pub stats: bool,
+    pub warn_lines: Option<usize>,
+    pub min_level: Option<LogLevel>,
+    pub confirm_quit: bool,
+    pub force_color: bool,
+    pub index_headers: bool,
+    pub diff_output: bool,
+    pub log_file: Option<PathBuf>,
+    pub json: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub time_limit: Option<Duration>,
+    /// How long tasks may go without printing anything before the session is ended.
+    pub shutdown_on_idle: Option<Duration>,
+    pub name_from: NameFrom,
+    /// How often time-based state, like the session timer, stats and idleness, is updated.
+    pub poll_interval: Duration,
+    /// A shell command run once all tasks have exited on their own.
+    pub on_complete: Option<String>,
+    pub search_history: Option<PathBuf>,
+    pub expand_on_failure: bool,
+    pub interleave: bool,
+    pub auto_reorder: bool,
+    pub counts: bool,
+    pub tail_on_exit: Option<usize>,
+    pub summary_sort: SummarySort,
+    pub echo_command: bool,
+    pub annotate_exit: bool,
+    /// Whether tasks start with an empty environment instead of congregation's.
+    pub env_inherit_none: bool,
+    /// Lines with more than this percentage of unprintable characters are treated as binary.
+    pub binary_threshold: u8,
+    /// Overrides the terminal's width for the interactive view.
+    pub width: Option<u16>,
+    /// Overrides the terminal's height for the interactive view.
+    pub height: Option<u16>,
+    pub tasks_per_page: Option<usize>,
+    pub alt_screen: bool,
+    pub key_hints: bool,
+    pub pager: bool,
+    /// Start delays of tasks given by name or number, applied to them once they're parsed.
+    pub start_delays: Vec<(String, Duration)>,
+    /// Finds the level of a log line, in its first capture group.
+    pub level_pattern: Regex,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            separate_stderr: false,
+            colorize_lines: false,
+            follow_threshold: 1,
+            scroll_step: 1,
+            page_step: None,
+            start_paused: false,
+            plain: false,
+            grep: None,
+            prefix_only_errors: false,
+            prefix_format: "[{name}]".into(),
+            control: None,
+            write_status: None,
+            tab_width: 8,
+            label: None,
+            buffer_output: false,
+            theme: Theme::default(),
+            raw: false,
+            stats: false,
+            warn_lines: None,
+            min_level: None,
+            confirm_quit: false,
+            force_color: true,
+            index_headers: false,
+            diff_output: false,
+            log_file: None,
+            json: None,
+            replay: None,
+            time_limit: None,
+            shutdown_on_idle: None,
+            name_from: NameFrom::Dir,
+            poll_interval: Duration::from_millis(100),
+            on_complete: None,
+            search_history: None,
+            expand_on_failure: false,
+            interleave: false,
+            auto_reorder: false,
+            counts: false,
+            tail_on_exit: None,
+            summary_sort: SummarySort::Input,
+            echo_command: false,
+            annotate_exit: false,
+            env_inherit_none: false,
+            binary_threshold: 30,
+            width: None,
+            height: None,
+            tasks_per_page: None,
+            alt_screen: true,
+            key_hints: true,
+            pager: false,
+            start_delays: Vec::new(),
+            level_pattern: Regex::new(DEFAULT_LEVEL_PATTERN).unwrap(),
+        }
+    }
+}
+
+fn option_value<T: FromStr>(
+    args: &mut Peekable<Args>,
+    flag: &str,
+    expected: &str,
+) -> Result<T, Error> {
+    flag_value(args, flag, expected, "invalid syntax".into())
+}
+
+fn flag_value<T: FromStr>(
+    args: &mut Peekable<impl Iterator<Item = String>>,
+    flag: &str,
+    expected: &str,
+    error_title: String,
+) -> Result<T, Error> {
+    let error = |message| Error {
+        title: error_title.clone(),
+        message,
+        ..Error::default()
+    };
+
+    let value = args
+        .next()
+        .ok_or_else(|| error(format!("expected {expected} after {flag}")))?;
+    value.parse().map_err(|_| {
+        error(format!(
+            "invalid value '{value}' for {flag}, expected {expected}"
+        ))
+    })
+}
+
+/// Whether an argument starts the next task, which ends the arguments of the one before.
+fn starts_task(arg: &str) -> bool {
+    arg == "run" || arg == "--task"
+}
+
+/// Parses the JSON task description after `--task`, which has the same fields as a control
+/// message and can be mixed with `run` tasks.
+fn parse_json_task(
+    args: &mut Peekable<Args>,
+    task_count: i32,
+    name_from: NameFrom,
+) -> Result<TaskDef, Error> {
+    let error = |message| Error {
+        title: format!("invalid syntax (in task {})", task_count + 1),
+        message,
+        notes: vec![r#"e.g. --task '{"command": "npm run dev", "name": "web"}'"#.into()],
+        ..Error::default()
+    };
+
+    let json = args
+        .next()
+        .ok_or_else(|| error("expected a JSON object after --task".into()))?;
+    let message: ControlMessage = serde_json::from_str(&json)
+        .map_err(|err| error(format!("invalid JSON for --task: {err}")))?;
+    message.into_task_def(task_count as usize, name_from)
+}
+
+fn duration_value(args: &mut Peekable<Args>, flag: &str) -> Result<Duration, Error> {
+    let value: String = option_value(args, flag, "a duration")?;
+    parse_duration(&value).ok_or_else(|| Error {
+        title: "invalid syntax".into(),
+        message: format!("invalid value '{value}' for {flag}, expected a duration"),
+        notes: vec![
+            "durations are given in seconds, or with a unit like '90s', '5m' or '1h'".into(),
+        ],
+        ..Error::default()
+    })
+}
+
+/// Reads environment variables from a file with one `NAME=value` per line, like a `.env` file,
+/// and adds them to `variables`. Empty lines and lines starting with `#` are ignored, and quotes
+/// around values are removed. Values may refer to the variables before them, see
+/// [`interpolate_env`], unless they're in single quotes.
+fn read_env_file(path: &str, variables: &mut Vec<(String, String)>) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read environment from '{path}': {err}"))?;
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("expected 'NAME=value' in {path}:{}", i + 1));
+        };
+        let value = value.trim();
+        let unquoted = ['"', '\''].iter().find_map(|quote| {
+            value
+                .strip_prefix(*quote)
+                .and_then(|value| value.strip_suffix(*quote))
+                .map(|value| (*quote, value))
+        });
+        let value = match unquoted {
+            Some(('\'', value)) => value.to_owned(),
+            _ => interpolate_env(unquoted.map_or(value, |(_, value)| value), variables)
+                .map_err(|err| format!("{err} in {path}:{}", i + 1))?,
+        };
+        variables.push((key.trim().to_owned(), value));
+    }
+    Ok(())
+}
+
+/// Parses a duration like `90`, `90s`, `5m`, `1h` or `250ms`, where plain numbers are seconds.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number = number.parse::<u64>().ok()?;
+    Some(match unit {
+        "ms" => Duration::from_millis(number),
+        "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number * 60),
+        "h" => Duration::from_secs(number * 3600),
+        _ => return None,
+    })
+}
+
+fn parse_option(args: &mut Peekable<Args>, flag: &str, options: &mut Options) -> Result<(), Error> {
+    match flag {
+        "--separate-stderr" => options.separate_stderr = true,
+        "--colorize-lines" => options.colorize_lines = true,
+        "--start-paused" => options.start_paused = true,
+        "--plain" => options.plain = true,
+        "--raw" => options.raw = true,
+        "--color-when" => {
+            // applied right away, so errors about the following options respect it too
+            set_color_when(option_value(args, flag, "'auto', 'always' or 'never'")?);
+        }
+        "--stats" => options.stats = true,
+        "--confirm-quit" => options.confirm_quit = true,
+        "--index-headers" => options.index_headers = true,
+        "--diff-output" => options.diff_output = true,
+        "--expand-on-failure" => options.expand_on_failure = true,
+        "--interleave" => options.interleave = true,
+        "--auto-reorder" => options.auto_reorder = true,
+        "--counts" => options.counts = true,
+        "--echo-command" => options.echo_command = true,
+        "--annotate-exit" => options.annotate_exit = true,
+        "--env-inherit-none" => options.env_inherit_none = true,
+        "--no-alt-screen" => options.alt_screen = false,
+        "--no-hints" => options.key_hints = false,
+        "--pager" => options.pager = true,
+        "--delay-start" => {
+            let value: String = option_value(args, flag, "'<task>=<duration>'")?;
+            let delay = value
+                .split_once('=')
+                .and_then(|(task, delay)| Some((task.to_owned(), parse_duration(delay)?)));
+            let Some(delay) = delay else {
+                return Err(Error {
+                    title: "invalid syntax".into(),
+                    message: format!(
+                        "invalid value '{value}' for {flag}, expected '<task>=<duration>'"
+                    ),
+                    notes: vec!["tasks are given by name or number, e.g. 'db=2s' or '3=1m'".into()],
+                    ..Error::default()
+                });
+            };
+            options.start_delays.push(delay);
+        }
+        "--tasks-per-page" => {
+            let per_page: usize = option_value(args, flag, "a number of tasks")?;
+            // there's nothing to page through without any tasks on a page
+            options.tasks_per_page = Some(per_page).filter(|per_page| *per_page > 0);
+        }
+        "--tail-on-exit" => {
+            options.tail_on_exit = Some(option_value(args, flag, "a number of lines")?)
+        }
+        "--summary-sort" => options.summary_sort = option_value(args, flag, "'input' or 'status'")?,
+        "--no-force-color" => options.force_color = false,
+        "--warn-lines" => {
+            options.warn_lines = Some(option_value(args, flag, "a number of lines per second")?)
+        }
+        "--buffer-output" => options.buffer_output = true,
+        "--write-status" => options.write_status = Some(option_value(args, flag, "a path")?),
+        "--log-file" => options.log_file = Some(option_value(args, flag, "a path")?),
+        "--json" => options.json = Some(option_value(args, flag, "a path")?),
+        "--time-limit" => options.time_limit = Some(duration_value(args, flag)?),
+        "--name-from" => {
+            options.name_from = option_value(args, flag, "'dir', 'command' or 'index'")?
+        }
+        "--width" | "--height" => {
+            let size: u16 = option_value(args, flag, "a number of cells")?;
+            if size == 0 {
+                return Err(Error {
+                    title: "invalid syntax".into(),
+                    message: format!("{flag} must be larger than 0"),
+                    ..Error::default()
+                });
+            }
+            match flag {
+                "--width" => options.width = Some(size),
+                _ => options.height = Some(size),
+            }
+        }
+        "--binary-threshold" => {
+            let percent: u8 = option_value(args, flag, "a percentage")?;
+            if percent > 100 {
+                return Err(Error {
+                    title: "invalid syntax".into(),
+                    message: format!("invalid value '{percent}' for {flag}, expected a percentage"),
+                    ..Error::default()
+                });
+            }
+            options.binary_threshold = percent;
+        }
+        "--poll-interval" => {
+            let interval = duration_value(args, flag)?;
+            if interval.is_zero() {
+                return Err(Error {
+                    title: "invalid syntax".into(),
+                    message: format!("{flag} must be longer than 0"),
+                    ..Error::default()
+                });
+            }
+            options.poll_interval = interval;
+        }
+        "--shutdown-on-idle" => options.shutdown_on_idle = Some(duration_value(args, flag)?),
+        "--on-complete" => options.on_complete = Some(option_value(args, flag, "a command")?),
+        "--search-history" => options.search_history = Some(option_value(args, flag, "a path")?),
+        "--replay" => options.replay = Some(option_value(args, flag, "a path")?),
+        "--control" => options.control = Some(option_value(args, flag, "a path")?),
+        "--prefix-format" => options.prefix_format = option_value(args, flag, "a format")?,
+        "--prefix-only-errors" => options.prefix_only_errors = true,
+        "--grep" => {
+            let pattern: String = option_value(args, flag, "a regular expression")?;
+            options.grep = Some(Regex::new(&pattern).map_err(|err| Error {
+                title: "invalid syntax".into(),
+                message: format!("invalid regular expression '{pattern}' for {flag}"),
+                notes: vec![err.to_string()],
+                ..Error::default()
+            })?);
+        }
+        "--status-text" => {
+            let value: String = option_value(args, flag, "'<key>=<text>'")?;
+            let valid = value
+                .split_once('=')
+                .is_some_and(|(key, text)| options.theme.set(key, text.to_owned()));
+            if !valid {
+                return Err(Error {
+                    title: "invalid syntax".into(),
+                    message: format!("invalid value '{value}' for {flag}, expected '<key>=<text>'"),
+                    notes: vec![format!("valid keys: {}", Theme::KEYS.join(", "))],
+                    ..Error::default()
+                });
+            }
+        }
+        "--min-level" => {
+            options.min_level = Some(option_value(
+                args,
+                flag,
+                "'trace', 'debug', 'info', 'warn' or 'error'",
+            )?)
+        }
+        "--level-pattern" => {
+            let pattern: String = option_value(args, flag, "a regular expression")?;
+            options.level_pattern = Regex::new(&pattern).map_err(|err| Error {
+                title: "invalid syntax".into(),
+                message: format!("invalid regular expression '{pattern}' for {flag}"),
+                notes: vec![err.to_string()],
+                ..Error::default()
+            })?;
+        }
+        "--label" => options.label = Some(option_value(args, flag, "a label")?),
+        "--tab-width" => {
+            options.tab_width = option_value(args, flag, "a number of columns")?;
+            if options.tab_width == 0 {
+                return Err(Error {
+                    title: "invalid syntax".into(),
+                    message: format!("invalid value '0' for {flag}, expected a number of columns"),
+                    ..Error::default()
+                });
+            }
+        }
+        "--scroll-step" => options.scroll_step = option_value(args, flag, "a number of lines")?,
+        "--page-step" => options.page_step = Some(option_value(args, flag, "a number of lines")?),
+        "--follow-threshold" => {
+            options.follow_threshold = option_value(args, flag, "a number of lines")?
+        }
+        _ => {
+            return Err(Error {
+                title: "invalid syntax".into(),
+                message: format!("unknown option {flag}"),
+                notes: vec!["options must come before the first 'run' keyword".into()],
+                ..Error::default()
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_args() -> Result<(Options, Vec<TaskDef>), Error> {
     let mut args = std::env::args().peekable();
     let name = args
         .next()
@@ -167,19 +901,67 @@ pub fn parse_args() -> Result<Vec<TaskDef>, Error> {
         })
         .unwrap_or("congregation".into());
 
+    let mut options = Options::default();
     let mut tasks = Vec::new();
-    while args.peek().is_some() {
-        if args.peek().is_some_and(|arg| {
-            matches!(arg.as_str(), "-h" | "--help") || arg.to_lowercase().starts_with("h")
-        }) {
+    while let Some(arg) = args.peek() {
+        if matches!(arg.as_str(), "-h" | "--help") || arg.to_lowercase().starts_with("h") {
             print_help(&name);
-            return Ok(Vec::new());
+            return Ok((options, Vec::new()));
         }
 
-        tasks.push(parse_task(&mut args, tasks.len() as i32)?);
+        if arg == "--task" {
+            args.next();
+            tasks.push(parse_json_task(
+                &mut args,
+                tasks.len() as i32,
+                options.name_from,
+            )?);
+            continue;
+        }
+
+        if tasks.is_empty() && arg.starts_with('-') {
+            let flag = args.next().unwrap();
+            parse_option(&mut args, &flag, &mut options)?;
+            continue;
+        }
+
+        tasks.push(parse_task(
+            &mut args,
+            tasks.len() as i32,
+            options.name_from,
+        )?);
     }
 
-    if tasks.is_empty() {
+    if options.replay.is_some() && !tasks.is_empty() {
+        return Err(Error {
+            title: "invalid syntax".into(),
+            message: "--replay shows a recorded session and can't run tasks".into(),
+            notes: vec!["remove the 'run' arguments to replay the session".into()],
+            ..Error::default()
+        });
+    }
+
+    for (task, delay) in &options.start_delays {
+        let position = tasks
+            .iter()
+            .position(|def: &TaskDef| def.name == *task)
+            .or_else(|| {
+                let number = task.parse::<usize>().ok()?;
+                (1..=tasks.len()).contains(&number).then(|| number - 1)
+            });
+        let Some(position) = position else {
+            return Err(Error {
+                title: "invalid syntax".into(),
+                message: format!("unknown task '{task}' for --delay-start"),
+                notes: vec!["tasks are given by name or by their number, starting at 1".into()],
+                ..Error::default()
+            });
+        };
+        tasks[position].start_delay = Some(*delay);
+    }
+
+    // tasks can also be added later through the control channel
+    if tasks.is_empty() && options.control.is_none() && options.replay.is_none() {
         return Err(Error {
             title: "no tasks specified!".into(),
             message: "please list some commands to execute using the 'run' keyword".into(),
@@ -188,5 +970,73 @@ pub fn parse_args() -> Result<Vec<TaskDef>, Error> {
         });
     }
 
-    Ok(tasks)
+    Ok((options, tasks))
+}
+
+pub fn parse_color(arg: &str) -> Option<Color> {
+    if arg.len() != 6 {
+        return None;
+    }
+
+    let r = u8::from_str_radix(arg.get(0..2)?, 16).ok()?;
+    let g = u8::from_str_radix(arg.get(2..4)?, 16).ok()?;
+    let b = u8::from_str_radix(arg.get(4..6)?, 16).ok()?;
+
+    Some(Color::Rgb { r, g, b })
+}
+
+/// Quotes an argument so the task's shell passes it to the command as-is.
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_owned();
+    }
+
+    if cfg!(windows) {
+        // cmd.exe doesn't treat backslashes as escapes, a quote is doubled instead
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<TaskDef, Error> {
+        let mut args = args.iter().map(|arg| arg.to_string()).peekable();
+        parse_task(&mut args, 0, NameFrom::Dir)
+    }
+
+    #[test]
+    fn interpolates_env_values_with_the_env_file_in_scope() {
+        let path = env::temp_dir().join(format!("congregation-test-{}.env", std::process::id()));
+        std::fs::write(
+            &path,
+            "HOST=db\nURL=\"postgres://${HOST}:${PORT:-5432}\"\nRAW='${HOST}'\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        let def = parse(&["run", "true", "--env-file", path, "-e", "NAME=${HOST}-1"]);
+        std::fs::remove_file(path).unwrap();
+
+        let def = def.unwrap();
+        let value = |key: &str| {
+            let found = def.env.iter().find(|(name, _)| name == key);
+            found.map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("URL"), Some("postgres://db:5432"));
+        assert_eq!(value("RAW"), Some("${HOST}"));
+        assert_eq!(value("NAME"), Some("db-1"));
+    }
+
+    #[test]
+    fn fails_on_undefined_variables_in_env_values() {
+        let err = parse(&["run", "true", "-e", "URL=${CONGREGATION_TEST_UNSET}"]).unwrap_err();
+        assert!(err.message.contains("'CONGREGATION_TEST_UNSET' is not set"));
+        let def = parse(&["run", "true", "-e", "URL=${CONGREGATION_TEST_UNSET:-x}"]).unwrap();
+        assert_eq!(def.env, vec![("URL".to_owned(), "x".to_owned())]);
+    }
 }
@@ -0,0 +1,58 @@
+use std::{env, io::IsTerminal, str::FromStr, sync::OnceLock};
+
+/// When to style output, as chosen with `--color-when`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorWhen {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(()),
+        }
+    }
+}
+
+static COLOR_WHEN: OnceLock<ColorWhen> = OnceLock::new();
+
+/// Overrides the color detection for all output. Only the first call has an effect.
+pub fn set_color_when(when: ColorWhen) {
+    if COLOR_WHEN.set(when).is_ok() {
+        match when {
+            ColorWhen::Always => crossterm::style::force_color_output(true),
+            ColorWhen::Never => crossterm::style::force_color_output(false),
+            ColorWhen::Auto => {}
+        }
+    }
+}
+
+/// Decides whether output written to `stream` should be styled.
+///
+/// `--color-when always` or `never` takes precedence over everything else. Otherwise,
+/// `NO_COLOR` (<https://no-color.org>) disables styling, `CLICOLOR_FORCE` enables it even when
+/// the output isn't a terminal, and output is styled only when it goes to a terminal.
+pub fn color_enabled(stream: &impl IsTerminal) -> bool {
+    match COLOR_WHEN.get() {
+        Some(ColorWhen::Always) => return true,
+        Some(ColorWhen::Never) => return false,
+        Some(ColorWhen::Auto) | None => {}
+    }
+
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+
+    if env::var_os("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0") {
+        return true;
+    }
+
+    stream.is_terminal()
+}
@@ -0,0 +1,214 @@
+use crate::{
+    arg_parser::{parse_task, NameFrom},
+    diagnostics::Error,
+    dir_config::interpolate_env,
+    task::TaskDef,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+use tokio::sync::mpsc::Sender;
+
+/// A task to add to the running session, sent as a line of JSON over the control channel, or
+/// given up front with `--task`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControlMessage {
+    command: String,
+    name: Option<String>,
+    dir: Option<String>,
+    color: Option<String>,
+    restart: Option<u32>,
+    retry_backoff: Option<String>,
+    retry_on: Option<String>,
+    crashloop_threshold: Option<usize>,
+    order: Option<i32>,
+    #[serde(default)]
+    success_codes: Vec<i32>,
+    nice: Option<i32>,
+    group: Option<String>,
+    #[serde(default)]
+    watch: Vec<String>,
+    input: Option<String>,
+    input_file: Option<String>,
+    env_passthrough: Option<Vec<String>>,
+    /// Variables to set for the task, by name.
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    env_file: Option<String>,
+    #[serde(default)]
+    login: bool,
+    health_url: Option<String>,
+    ready_timeout: Option<String>,
+    encoding: Option<String>,
+}
+
+impl ControlMessage {
+    /// Turns the message into a task definition by parsing it like the equivalent `run` arguments,
+    /// so it gets the same validation and directory defaults as tasks from the command line.
+    pub fn into_task_def(self, task_count: usize, name_from: NameFrom) -> Result<TaskDef, Error> {
+        let mut args = vec!["run".to_owned(), self.command];
+        let mut push_flag = |flag: &str, value: String| {
+            args.push(flag.to_owned());
+            args.push(value);
+        };
+
+        if let Some(name) = self.name {
+            push_flag("-n", name);
+        }
+        if let Some(dir) = self.dir {
+            push_flag("-d", dir);
+        }
+        if let Some(color) = self.color {
+            push_flag("-c", color);
+        }
+        if let Some(delay) = self.restart {
+            push_flag("-r", delay.to_string());
+        }
+        if let Some(backoff) = self.retry_backoff {
+            push_flag("--retry-backoff", backoff);
+        }
+        if let Some(pattern) = self.retry_on {
+            push_flag("--retry-on", pattern);
+        }
+        if let Some(threshold) = self.crashloop_threshold {
+            push_flag("--crashloop-threshold", threshold.to_string());
+        }
+        if !self.success_codes.is_empty() {
+            let codes: Vec<String> = self.success_codes.iter().map(i32::to_string).collect();
+            push_flag("--success-codes", codes.join(","));
+        }
+        if let Some(nice) = self.nice {
+            push_flag("--nice", nice.to_string());
+        }
+        if let Some(order) = self.order {
+            push_flag("--order", order.to_string());
+        }
+        if let Some(group) = self.group {
+            push_flag("--group", group);
+        }
+        for pattern in self.watch {
+            push_flag("--watch", pattern);
+        }
+        if let Some(input) = self.input {
+            push_flag("--input", input);
+        }
+        if let Some(path) = self.input_file {
+            push_flag("--input-file", path);
+        }
+        if let Some(keys) = self.env_passthrough {
+            push_flag("--env-passthrough", keys.join(","));
+        }
+        if let Some(path) = self.env_file {
+            push_flag("--env-file", path);
+        }
+        for (key, value) in self.env {
+            push_flag("-e", format!("{key}={value}"));
+        }
+        if let Some(url) = self.health_url {
+            push_flag("--health-url", url);
+        }
+        if let Some(timeout) = self.ready_timeout {
+            push_flag("--ready-timeout", timeout);
+        }
+        if let Some(encoding) = self.encoding {
+            push_flag("--encoding", encoding);
+        }
+        if self.login {
+            args.push("--login".to_owned());
+        }
+
+        let mut def = parse_task(
+            &mut args.into_iter().peekable(),
+            task_count as i32,
+            name_from,
+        )?;
+        // references are resolved up front like in the other values, so a missing variable is
+        // reported instead of silently expanding to nothing in the shell
+        def.command = interpolate_env(&def.command, &def.env).map_err(|err| Error {
+            title: format!("invalid syntax (in task {})", task_count + 1),
+            message: format!("invalid command: {err}"),
+            notes: vec!["a default can be given with ${VAR:-default}".into()],
+            ..Error::default()
+        })?;
+        Ok(def)
+    }
+}
+
+/// Reads control messages from the given path on a separate thread, one JSON object per line.
+///
+/// Opening a fifo blocks until something writes to it, which is why this doesn't use tokio's
+/// file I/O: its blocking threads would keep the runtime from shutting down.
+pub fn spawn_control_reader(path: PathBuf, tx: Sender<Result<ControlMessage, Error>>) {
+    let error = |message| Error {
+        title: "invalid control message".into(),
+        message,
+        ..Error::default()
+    };
+
+    std::thread::spawn(move || loop {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                let _ = tx.blocking_send(Err(Error {
+                    title: "failed to open control channel".into(),
+                    message: format!("{}: {err}", path.display()),
+                    ..Error::default()
+                }));
+                return;
+            }
+        };
+
+        for line in BufReader::new(file).lines() {
+            let message = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => serde_json::from_str(&line).map_err(|err| error(err.to_string())),
+                Err(err) => Err(error(err.to_string())),
+            };
+            if tx.blocking_send(message).is_err() {
+                return;
+            }
+        }
+
+        // a fifo ends whenever its last writer closes it, so it's reopened to wait for the next one
+        if !is_fifo(&path) {
+            return;
+        }
+    });
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_def(json: &str) -> Result<TaskDef, Error> {
+        let message: ControlMessage = serde_json::from_str(json).unwrap();
+        message.into_task_def(0, NameFrom::Dir)
+    }
+
+    #[test]
+    fn interpolates_the_command() {
+        let def = task_def(r#"{"command": "serve ${PORT}", "env": {"PORT": "3000"}}"#).unwrap();
+        assert_eq!(def.command, "serve 3000");
+        let def = task_def(r#"{"command": "serve ${CONGREGATION_TEST_UNSET:-80}"}"#).unwrap();
+        assert_eq!(def.command, "serve 80");
+        assert!(task_def(r#"{"command": "serve ${CONGREGATION_TEST_UNSET}"}"#).is_err());
+    }
+}
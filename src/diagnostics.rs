@@ -1,3 +1,4 @@
+use crate::color::color_enabled;
 use crossterm::style::Stylize;
 use indoc::printdoc;
 use std::fmt::Display;
@@ -18,7 +19,7 @@ impl Display for Error {
         if self.examples.is_empty() {
             writeln!(f, "{}", self.message)?;
         } else {
-            writeln!(f, "")?;
+            writeln!(f)?;
             writeln!(f, "{}:", self.message)?;
             for example in &self.examples {
                 writeln!(f, "{} {}", "│".dark_grey(), example)?;
@@ -42,6 +43,16 @@ impl Display for Error {
     }
 }
 
+impl Error {
+    pub fn print(&self) {
+        if color_enabled(&std::io::stderr()) {
+            eprint!("{self}");
+        } else {
+            eprint!("{}", strip_ansi_escapes::strip_str(self.to_string()));
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
         Self {
@@ -57,16 +68,188 @@ pub fn print_help(name: &str) {
     printdoc!("
     Run multiple parallel tasks with grouped output
 
-    Usage: {name} <task> [<task> ...]
+    Usage: {name} [<options>] <task> [<task> ...]
+
+    Options:
+      --separate-stderr          Show each task's stderr in its own section below stdout (toggle with 's')
+      --color-when <when>        Whether to use colors: 'auto' (the default) detects whether the
+                                 output is a terminal, 'always' and 'never' override that as well
+                                 as NO_COLOR and CLICOLOR_FORCE
+      --env-inherit-none         Start tasks with an empty environment besides a minimal PATH and
+                                 the variables given with -e and --env-file. Many commands need
+                                 more, like HOME, so expect to add some
+      --no-force-color           Don't set FORCE_COLOR=1 and CLICOLOR_FORCE=1 for tasks, which
+                                 makes many tools print colors even though their output is piped
+      --diff-output              Mark lines that are new (+) or missing (-) compared to a task's
+                                 previous run once it exits, e.g. to spot changes in test output
+      --index-headers            Start each task header with the task's number, e.g. '[3] build'
+      --colorize-lines           Tint each task's output lines with a dim shade of its color
+      --follow-threshold <lines> Keep following new output while the cursor is at most this many
+                                 lines above the bottom (defaults to 1)
+      --scroll-step <lines>      Lines moved by the up and down keys (defaults to 1)
+      --page-step <lines>        Lines moved by page up and down, half as many with ctrl+u/ctrl+d
+                                 (defaults to the height of the terminal)
+      --start-paused             Wait for space to be pressed before starting the tasks
+      --time-limit <duration>    Stop all tasks once the session ran this long, e.g. '90s', '5m' or
+                                 '1h', and exit with an error
+      --poll-interval <duration> How often the session timer, --stats, --warn-lines and
+                                 --shutdown-on-idle are updated (defaults to '100ms'). New
+                                 output and key presses are always drawn right away
+      --shutdown-on-idle <duration>
+                                 Stop all tasks once none of them printed anything for this long,
+                                 e.g. '30s', to run until things settle down
+      --confirm-quit             Ask to press q or ctrl+c again within 2 seconds before quitting
+                                 while tasks are still running
+      --plain                    Stream output line by line, prefixed with the task name, instead
+                                 of showing the interactive view
+      --stats                    Show the pid, CPU and memory usage of running tasks, updated every
+                                 2 seconds
+      --counts                   Show how many lines and bytes each task printed next to its status,
+                                 and the totals once all tasks are done
+      --warn-lines <lines>       Warn in a task's header while it prints more than this many lines
+                                 per second, to spot runaway tasks
+      --raw                      Keep task output exactly as printed, without removing escape
+                                 sequences, expanding tabs or replacing control characters. They
+                                 may mess up the interactive view, but are kept in --plain output
+      --binary-threshold <percent>
+                                 Summarize lines with more than this percentage of unprintable
+                                 characters as binary output instead of showing them (defaults to
+                                 30, 100 shows everything). --raw shows everything as well
+      --buffer-output            Show each task's output only once it exits: collapsed until then in
+                                 the interactive view, printed in one block in --plain mode
+      --tail-on-exit <lines>     Only print the last lines of each task when printing all output on
+                                 exit, e.g. when the output isn't a terminal
+      --name-from <source>       Default name of tasks without -n: 'dir' (the default) names them
+                                 after their -d directory or their number and command, 'command'
+                                 after their command and 'index' after their number, e.g. '#3'
+      --summary-sort <order>     Order of the tasks when printing all output on exit: 'input' (the
+                                 default) or 'status', which puts failed tasks first, then the
+                                 ones that succeeded, then the ones that didn't finish
+      --annotate-exit            Add how long each run took to its exit in --plain mode and
+                                 --log-file, e.g. '[build] completed after 12s'
+      --echo-command             Start each run's output with the command and its working directory
+      --no-alt-screen            Draw the interactive view on the terminal's main screen instead of
+                                 the alternate one
+      --width <columns>          Draw the interactive view this wide instead of as wide as the
+                                 terminal, for a fixed layout, e.g. in recordings
+      --height <rows>            Draw the interactive view this high instead of as high as the
+                                 terminal
+      --pager                    Show the output printed on exit in $PAGER (or less) when it's
+                                 printed to a terminal
+      --delay-start <task>=<duration>
+                                 Wait before starting a task, given by name or number, e.g. 'db=2s'
+      --no-hints                 Hide the key hints in the footer
+      --tasks-per-page <n>       Show only n tasks at a time, paging through them with '<' and '>'
+      --interleave               Show the output of all tasks as one stream in the order it arrived,
+                                 instead of grouped by task (toggle with 'i')
+      --auto-reorder             Show failed tasks first, then running ones, then all others, as
+                                 their status changes (toggle with 'a')
+      --expand-on-failure        Expand a task and jump to it when it fails
+      --grep <regex>             Only print lines matching the expression in --plain mode
+      --prefix-only-errors       Only print lines written to stderr in --plain mode
+      --prefix-format <format>   Line prefix in --plain mode (defaults to '[{{name}}]'), where {{name}}
+                                 is the task name and {{elapsed}} the time since the task started
+      --status-text <key>=<text> Replace the text of a task status, e.g. 'completed=done'. Keys are
+                                 pending, waiting ({{secs}}), running, stopping, stopped,
+                                 completed, failed ({{code}}), killed ({{signal}}), paused,
+                                 timed-out, starting, ready, not-ready ({{secs}}), restarting,
+                                 restarting-in ({{secs}}), file-changed, crash-looping,
+                                 success-glyph and failure-glyph
+      --min-level <level>        Hide log lines below a level: trace, debug, info, warn or error
+                                 (change it with 'v'). Lines without a level are always shown
+      --level-pattern <regex>    Expression finding the level of a line in its first capture group
+                                 (defaults to a level at the start of the line, like 'INFO' or '[warn]')
+      --search-history <path>    Remember searches across sessions in a file
+      --label <label>            Name of the run, shown in the footer and the terminal title
+      --tab-width <columns>      Width of tab stops in task output (defaults to 8)
+      --log-file <path>          Also write all output to a file, prefixed with the UTC time, the
+                                 task's id, the stream and the task name
+      --json <path>              Also write all output and exit codes to a file, one JSON object per
+                                 line, e.g. {{\"id\": 0, \"task\": \"...\", \"time\": <ms>,
+                                 \"stream\": \"stdout\", \"text\": \"...\"}}
+      --replay <path>            Show a session recorded with --json instead of running tasks
+      --on-complete <command>    Run a shell command once all tasks have exited, but not when quitting
+                                 early. It runs last, after the interactive view is closed and
+                                 all output is printed. FAILED_COUNT holds the number of failed
+                                 tasks and TASK_STATUSES a tab-separated name and exit code per
+                                 line
+      --write-status <path>      Write each task's name and exit code to a file when exiting, one
+                                 tab-separated line per task
+      --control <path>           Read tasks to add while running from a fifo, one JSON object per line,
+                                 e.g. {{\"command\": \"...\", \"name\": \"...\", \"dir\": \"...\", \"color\": \"...\",
+                                 \"restart\": <secs>, \"order\": <n>, \"watch\": [\"<glob>\", ...]}}
 
     Task syntax:
-      run <command> [-d <dir>] [-n <name>] [-c <rrggbb>]
+      run <command> [-d <dir>] [-n <name>] [-c <rrggbb>] [-- <args>...]
+      --task <json>
+
+      A task can also be given as a JSON object with the fields accepted by --control, e.g.
+      --task '{{\"command\": \"...\", \"name\": \"...\", \"env\": {{\"NAME\": \"value\"}}}}', before,
+      after or between 'run' tasks
 
       Options:
-        <command>     The shell command to run (wrap in quotes if it contains spaces)
+        <command>     The shell command to run (wrap in quotes if it contains spaces), or
+                      @<file> to run the contents of a file as the command
         -d <dir>      Working directory for the task (defaults to the current working directory)
-        -n <name>     Name of the task (used in task header, defaults to working directory or command)
+        -n <name>     Name of the task (used in task header, see --name-from for the default)
         -c <rrggbb>   Hex RGB color for task name (e.g., ff8800, defaults to white)
         -r [<secs>]   Restart the task on exit (defaults to 3s if no delay specified)
+        --retry-backoff <fixed|exponential>
+                      With -r, keep the delay fixed or double it after each failed run, up to
+                      5 minutes (defaults to fixed)
+        --retry-on <regex>
+                      Only restart the task when its output matched the expression during the
+                      run, even if it exited successfully (implies -r)
+        --crashloop-threshold <n>
+                      With -r, stop restarting the task once it failed more than n times within
+                      a minute, until it's restarted by hand
+        --success-codes <codes>
+                      Comma-separated exit codes that count as success besides 0, e.g. '1' for grep
+        --nice <n>    Run the task with the given niceness, higher meaning a lower CPU priority
+                      (maps to the closest priority class on Windows)
+        --order <n>   Position of the task in the output, lower first (defaults to 0, tasks
+                      with the same order keep the order they were given in)
+        --group <name>
+                      Show the task under a header with other tasks of the same group, which
+                      summarizes their status and collapses them together
+        --watch <glob>
+                      Restart the task when files in its directory matching the glob change
+                      (can be given multiple times)
+        --env-passthrough <names>
+                      Comma-separated environment variables to pass to the task, instead of all
+                      of them. Variables that aren't listed, including PATH, aren't set
+        -e <name>=<value>
+                      Set an environment variable for the task (can be given multiple times).
+                      The value can refer to variables as ${{VAR}} or ${{VAR:-default}}
+        --env-file <path>
+                      Set the environment variables in a file with one 'NAME=value' per line,
+                      where values can refer to variables like with -e
+        --login       Run the command in your shell ($SHELL) with your profile loaded, as in a
+                      terminal, instead of in 'sh' (ignored on Windows)
+        --encoding <label>
+                      Read the task's output in an encoding other than UTF-8, e.g.
+                      'windows-1252' for legacy code pages
+        --health-url <url>
+                      Show the task as starting until the http:// URL responds with a 2xx
+                      status, then as ready. It's requested every second
+        --ready-timeout <duration>
+                      With --health-url, show the task as not ready if the URL didn't respond
+                      successfully this long after the task started, e.g. '60s'
+        --input <text>
+                      Write the text to the task's stdin and close it afterwards
+        --input-file <path>
+                      Write the contents of a file to the task's stdin and close it afterwards
+        -- <args>...  Append the remaining arguments, up to the next 'run' or '--task', to the
+                      command, quoted so the shell passes them through unchanged
+
+      Directory defaults:
+        When -d is given, a .congregation file in that directory may provide defaults
+        for options not passed on the command line, one 'key = value' per line:
+          name = <name>
+          color = <rrggbb>
+
+    Environment:
+      NO_COLOR         Disable colors when set to a non-empty value
+      CLICOLOR_FORCE   Keep colors even when the output isn't a terminal
     ");
 }
@@ -0,0 +1,134 @@
+use crate::arg_parser::parse_color;
+use crossterm::style::Color;
+use std::{env, fs, io::ErrorKind, path::Path};
+
+const FILE_NAME: &str = ".congregation";
+
+/// Per-directory task defaults, read from a `.congregation` file in the task's working directory.
+///
+/// The file consists of `key = value` lines. Empty lines and lines starting with `#` are ignored.
+/// Values may refer to environment variables, see [`interpolate_env`].
+/// ```text
+/// name = api
+/// color = ff8800
+/// ```
+#[derive(Debug, Default)]
+pub struct DirConfig {
+    pub name: Option<String>,
+    pub color: Option<Color>,
+}
+
+pub fn read_dir_config(dir: &Path) -> Result<DirConfig, String> {
+    let path = dir.join(FILE_NAME);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(DirConfig::default()),
+        Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+    };
+
+    let mut config = DirConfig::default();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let location = format!("{}:{}", path.display(), i + 1);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("expected 'key = value' in {location}"));
+        };
+
+        let value =
+            interpolate_env(value.trim(), &[]).map_err(|err| format!("{err} in {location}"))?;
+        let value = value.as_str();
+        match key.trim() {
+            "name" => config.name = Some(value.to_owned()),
+            "color" => {
+                config.color = Some(
+                    parse_color(value)
+                        .ok_or_else(|| format!("invalid color '{value}' in {location}"))?,
+                )
+            }
+            key => return Err(format!("unknown key '{key}' in {location}")),
+        }
+    }
+
+    Ok(config)
+}
+
+/// Replaces `${VAR}` with the value of the environment variable, or `${VAR:-default}` with the
+/// default if the variable isn't set or empty, like a shell would. Referring to a variable that
+/// isn't set without a default is an error, so a missing variable doesn't go unnoticed.
+///
+/// `variables` are looked up before the environment, with later ones taking precedence, like
+/// those set for a task with `-e` or `--env-file`.
+pub fn interpolate_env(text: &str, variables: &[(String, String)]) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("missing '}}' after '{}'", &rest[start..]));
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        let value = match variables.iter().rev().find(|(key, _)| key == name) {
+            Some((_, value)) => Some(value.clone()),
+            None => env::var(name).ok(),
+        };
+        match (value, default) {
+            (Some(value), Some(default)) if value.is_empty() => result.push_str(default),
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => return Err(format!("environment variable '{name}' is not set")),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A variable no environment sets.
+    const UNSET: &str = "CONGREGATION_TEST_UNSET";
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn interpolates_given_variables_before_the_environment() {
+        let variables = vars(&[("HOST", "old"), ("HOST", "db"), ("PORT", "5432")]);
+        assert_eq!(
+            interpolate_env("${HOST}:${PORT}/app", &variables).unwrap(),
+            "db:5432/app"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_default() {
+        let text = format!("${{{UNSET}:-localhost}}");
+        assert_eq!(interpolate_env(&text, &[]).unwrap(), "localhost");
+        let variables = vars(&[(UNSET, "")]);
+        assert_eq!(interpolate_env(&text, &variables).unwrap(), "localhost");
+        let variables = vars(&[(UNSET, "db")]);
+        assert_eq!(interpolate_env(&text, &variables).unwrap(), "db");
+    }
+
+    #[test]
+    fn fails_on_undefined_variables() {
+        let err = interpolate_env(&format!("host=${{{UNSET}}}"), &[]).unwrap_err();
+        assert_eq!(err, format!("environment variable '{UNSET}' is not set"));
+        assert!(interpolate_env("${HOST", &[]).is_err());
+    }
+}
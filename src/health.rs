@@ -0,0 +1,132 @@
+use crate::task::{TaskMessage, TaskMessageKind};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+/// How often a task's health URL is requested until it's ready.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a single request may take before it counts as failed.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// An `http://` URL that a task is ready at once it responds with a 2xx status.
+#[derive(Debug, Clone)]
+pub struct HealthUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl FromStr for HealthUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("http://")
+            .ok_or("only http:// URLs are supported")?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        // IPv6 addresses are wrapped in brackets, since they contain colons themselves
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(rest) => {
+                let (host, port) = rest
+                    .split_once(']')
+                    .ok_or("missing ']' after IPv6 address")?;
+                match port {
+                    "" => (host, None),
+                    port => (
+                        host,
+                        Some(port.strip_prefix(':').ok_or("expected ':' after ']'")?),
+                    ),
+                }
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| format!("invalid port '{port}'"))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err("missing host".into());
+        }
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl HealthUrl {
+    /// Requests the URL once, returning whether it responded with a 2xx status. Refused
+    /// connections and timeouts just mean the task isn't ready yet.
+    async fn is_healthy(&self) -> bool {
+        let request = async {
+            let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+            let host = if self.host.contains(':') {
+                format!("[{}]", self.host)
+            } else {
+                self.host.clone()
+            };
+            let request = format!(
+                "GET {} HTTP/1.1\r\nHost: {host}:{}\r\nConnection: close\r\n\r\n",
+                self.path, self.port
+            );
+            stream.write_all(request.as_bytes()).await?;
+
+            let mut status_line = String::new();
+            BufReader::new(stream).read_line(&mut status_line).await?;
+            Ok::<_, std::io::Error>(status_line)
+        };
+
+        let Ok(Ok(status_line)) = tokio::time::timeout(HEALTH_TIMEOUT, request).await else {
+            return false;
+        };
+        // e.g. "HTTP/1.1 200 OK"
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .is_some_and(|code| code.len() == 3 && code.starts_with('2'))
+    }
+}
+
+/// Polls the URL until it's healthy, then tells the task it's ready. With a timeout, it gives up
+/// once that passed and tells the task it didn't become ready instead.
+pub fn spawn_health_check(
+    url: HealthUrl,
+    timeout: Option<Duration>,
+    id: usize,
+    tx: Sender<TaskMessage>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let poll = async {
+            let mut interval = tokio::time::interval(HEALTH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if url.is_healthy().await {
+                    return;
+                }
+            }
+        };
+        let kind = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, poll).await {
+                Ok(()) => TaskMessageKind::Ready,
+                Err(_) => TaskMessageKind::NotReady,
+            },
+            None => {
+                poll.await;
+                TaskMessageKind::Ready
+            }
+        };
+        let _ = tx.send(TaskMessage { task: id, kind }).await;
+    })
+}
@@ -1,89 +1,294 @@
 mod arg_parser;
+mod color;
+mod control;
 mod diagnostics;
+mod dir_config;
+mod health;
+mod plain;
 mod renderer;
+mod replay;
+mod sink;
+mod stats;
 mod task;
+mod theme;
 
-use crate::task::{TaskMessage, TaskMessageKind};
+use crate::renderer::format_elapsed;
+use crate::task::{
+    LogKind, LogLevel, LogLine, RestartReason, Stream, TaskExitReason, TaskMessage, TaskMessageKind,
+};
 use crate::{renderer::Renderer, task::TaskState};
 use arg_parser::parse_args;
-use crossterm::event::EventStream;
+use color::color_enabled;
+use control::spawn_control_reader;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::Stylize;
 use diagnostics::Error;
+use plain::PlainPrinter;
+use replay::read_replay;
+use sink::{open_file_sinks, Sink};
+use stats::StatsCollector;
+use std::io::IsTerminal;
+use std::path::Path;
 use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use task::Task;
 use tokio::sync::{broadcast, mpsc};
 use tokio_stream::StreamExt;
+use unicode_width::UnicodeWidthChar;
 
-async fn run() -> Result<(), Error> {
-    let tasks = parse_args()?;
-    if tasks.is_empty() {
-        return Ok(());
+/// How often resource usage is collected with `--stats`.
+const STATS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often line rates are checked with `--warn-lines`, which is given in lines per second.
+const LINE_RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+async fn run() -> Result<ExitCode, Error> {
+    let (options, tasks) = parse_args()?;
+    if tasks.is_empty() && options.control.is_none() && options.replay.is_none() {
+        return Ok(ExitCode::SUCCESS);
     }
 
     let (tx, mut rx) = mpsc::channel::<TaskMessage>(32);
 
-    let mut tasks: Vec<Task> = tasks
-        .into_iter()
-        .enumerate()
-        .map(|(id, task)| {
-            let mut task = Task::new(task, id, tx.clone());
-            task.run();
-            task
-        })
-        .collect();
+    let mut replay_order = None;
+    let mut tasks: Vec<Task> = match &options.replay {
+        Some(path) => {
+            let replay = read_replay(path, &tx)?;
+            replay_order = Some(replay.arrival_order);
+            replay.tasks
+        }
+        None => tasks
+            .into_iter()
+            .enumerate()
+            .map(|(id, task)| Task::new(task, id, tx.clone()))
+            .collect(),
+    };
+
+    // tools only need to be asked for colors if they're shown
+    let force_color = options.force_color && color_enabled(&std::io::stdout());
+    for task in &mut tasks {
+        task.start_watching()?;
+        // buffered output is only revealed once the task is done
+        task.collapsed = options.buffer_output;
+        task.force_color = force_color;
+        task.echo_command = options.echo_command;
+        task.clear_env = options.env_inherit_none;
+    }
+
+    let (control_tx, mut control_rx) = mpsc::channel(8);
+    if let Some(path) = &options.control {
+        spawn_control_reader(path.clone(), control_tx);
+    }
 
     let (interrupt_tx, mut interrupt_rx) = broadcast::channel(1);
 
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        // closing the terminal sends SIGHUP, which stops the tasks the same way
+        for kind in [SignalKind::terminate(), SignalKind::hangup()] {
+            let mut signals = signal(kind)?;
+            let interrupt_tx = interrupt_tx.clone();
+            tokio::spawn(async move {
+                while signals.recv().await.is_some() {
+                    let _ = interrupt_tx.send(());
+                }
+            });
+        }
+    }
+
     #[cfg(unix)]
     let _ = ctrlc::set_handler(move || {
         let _ = interrupt_tx.send(());
     });
 
-    let mut completed_task_count = 0;
-    let mut events = EventStream::new();
+    let mut interrupted = false;
+
+    // without a terminal there is nothing to draw or read input from,
+    // so the tasks just run and their output is printed once they're done
+    let mut interactive = !options.plain && std::io::stdout().is_terminal();
+    let mut plain = options.plain;
+    let mut events = None;
+    let mut sinks = open_file_sinks(&options)?;
 
-    let mut renderer = Renderer::new();
-    renderer.enter_screen()?;
+    let mut renderer = Renderer::new(&options);
+    if let Some(order) = replay_order {
+        renderer.set_arrival_order(&tasks, order);
+    }
+    if interactive {
+        match renderer.enter_screen() {
+            Ok(()) => events = Some(EventStream::new()),
+            Err(err) => {
+                Error {
+                    title: "failed to start the interactive view".into(),
+                    message: err.to_string(),
+                    notes: vec![
+                        "falling back to plain output".into(),
+                        "pass --plain to skip the interactive view".into(),
+                    ],
+                    ..Error::default()
+                }
+                .print();
+                interactive = false;
+                plain = true;
+            }
+        }
+    }
+    if plain {
+        sinks.push(Box::new(PlainPrinter::new(&options)));
+    }
+
+    // tasks can only be started by a keypress when there's a terminal to read it from
+    if !(options.start_paused && interactive) {
+        start_pending_tasks(&mut tasks, &mut renderer, &mut sinks, interactive);
+        if !tasks.is_empty() {
+            renderer.start_timer();
+        }
+    }
     renderer.draw_tasks(&tasks)?;
 
+    let mut stats = options.stats.then(StatsCollector::new);
+    // everything time-based is updated on the same tick, each part at its own pace
+    let mut poll_interval = tokio::time::interval(options.poll_interval);
+    // ticks missed while busy drawing output are caught up on by the next one
+    poll_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut stats_updated_at = None::<Instant>;
+    let mut line_rate_checked_at = Instant::now();
+    let mut last_output_at = Instant::now();
+    let time_limit = async {
+        match options.time_limit {
+            Some(limit) => tokio::time::sleep(limit).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(time_limit);
+    let mut timed_out = false;
+    let mut idled = false;
+
     loop {
+        // with a control channel, more tasks may still be added until the session is quit,
+        // and a replay stays open until it's quit as well. A replay's tasks are finished
+        // from the start, which is why this is checked before waiting for anything
+        let keep_open = (options.control.is_some() || (options.replay.is_some() && interactive))
+            && !renderer.is_quitting();
+        if !keep_open && tasks.iter().all(Task::is_finished) {
+            break;
+        }
+
         tokio::select! {
             Some(TaskMessage { task: id, kind }) = rx.recv() => match kind {
-                TaskMessageKind::Stdout(line) => {
+                TaskMessageKind::Output(stream, line) => {
                     let task = tasks.get_mut(id).unwrap();
-                    task.logs.push(strip_ansi_escapes::strip_str(line.trim()));
+                    task.line_total += 1;
+                    task.stderr_lines += (stream == Stream::Stderr) as usize;
+                    task.byte_total += line.len();
+                    if !options.raw && is_binary(&line, options.binary_threshold) {
+                        task.binary_lines += 1;
+                        let count = task.binary_lines;
+                        let lines = if count == 1 { "line" } else { "lines" };
+                        let text = format!("(binary output suppressed, {count} {lines})")
+                            .dark_grey()
+                            .italic()
+                            .to_string();
+                        // consecutive binary lines share one notice, even if other notices
+                        // were added after it in the meantime
+                        match task.logs.get_mut(task.binary_notice) {
+                            Some(notice) if task.binary_lines > 1 => notice.text = text,
+                            _ => {
+                                task.binary_notice = task.logs.len();
+                                task.logs.push(LogLine {
+                                    text,
+                                    stream,
+                                    kind: LogKind::Notice,
+                                    level: None,
+                                });
+                                for sink in &mut sinks {
+                                    sink.line(task, task.logs.last().unwrap());
+                                }
+                            }
+                        }
+                    } else {
+                        task.binary_lines = 0;
+                        let text = if options.raw {
+                            line.strip_suffix('\n').unwrap_or(&line).to_owned()
+                        } else {
+                            // leading whitespace is kept, it's the indentation tabs are expanded for
+                            clean_line(line.trim_end(), options.tab_width)
+                        };
+                        task.logs.push(LogLine {
+                            level: LogLevel::detect(&options.level_pattern, &text),
+                            text,
+                            stream,
+                            kind: LogKind::Output,
+                        });
+                        for sink in &mut sinks {
+                            sink.line(task, task.logs.last().unwrap());
+                        }
+                        if let Some(pattern) = &task.def.retry_on {
+                            let text = &task.logs.last().unwrap().text;
+                            task.retry_matched |= pattern.is_match(text);
+                        }
+                    }
+                    task.lines_since_check += 1;
+                    last_output_at = Instant::now();
 
                     renderer.draw_tasks(&tasks)?;
                 }
                 TaskMessageKind::Exited(reason) => {
                     let task = tasks.get_mut(id).unwrap();
+                    task.stop_health_check();
+                    let reason = match reason {
+                        TaskExitReason::Failed(code) if task.def.success_codes.contains(&code) => {
+                            TaskExitReason::Succeeded
+                        }
+                        reason => reason,
+                    };
+                    let mut failed = false;
 
                     match &task.state {
-                        TaskState::ForceRestarting => {
-                            task.run();
-                            renderer.draw_tasks(&tasks)?;
-                            continue;
+                        TaskState::ForceRestarting(_) => {
+                            if let Err(error) = task.run() {
+                                report_error(&mut renderer, interactive, &error);
+                            }
                         }
-
                         TaskState::Running { .. } => {
-                            if let Some(delay) = task.def.restart_delay_secs {
-                                task.start_restart_countdown(reason, delay);
-                                continue;
+                            if options.diff_output {
+                                task.diff_with_previous_run();
+                            }
+                            failed = reason != TaskExitReason::Succeeded;
+                            // a task that keeps failing is left alone instead of restarted again
+                            // and again, until it's restarted by hand
+                            task.crash_looping = failed && task.record_failure();
+                            // with --retry-on, only runs whose output matched are restarted
+                            let restart = (task.def.retry_on.is_none() || task.retry_matched)
+                                && !task.crash_looping;
+                            match task.def.restart_delay_secs.filter(|_| restart) {
+                                Some(delay) => task.start_restart_countdown(reason, delay),
+                                None => task.state = TaskState::Exited(reason),
                             }
-
-                            task.state = TaskState::Exited(reason);
                         }
-
-                        TaskState::Stopped => {},
-
+                        TaskState::Stopping => task.state = TaskState::Stopped,
                         _ => unreachable!()
                     }
 
-                    renderer.draw_tasks(&tasks)?;
-
-                    completed_task_count += 1;
-                    if completed_task_count == tasks.len() {
-                        break;
+                    if !matches!(task.state, TaskState::Running { .. }) {
+                        for sink in &mut sinks {
+                            sink.status(task);
+                        }
+                        if options.buffer_output {
+                            task.collapsed = false;
+                        }
+                    }
+                    if failed && options.expand_on_failure {
+                        task.collapsed = false;
+                        // there's only a view to scroll in interactively
+                        if interactive {
+                            renderer.focus_task(&tasks, id);
+                        }
                     }
+
+                    renderer.draw_tasks(&tasks)?;
                 }
                 TaskMessageKind::Restarting(secs) => {
                     let task = tasks.get_mut(id).unwrap();
@@ -95,32 +300,459 @@ async fn run() -> Result<(), Error> {
                 },
                 TaskMessageKind::Restart => {
                     let task = tasks.get_mut(id).unwrap();
-                    task.run();
-                    renderer.draw_tasks(&tasks)?;
+
+                    // the countdown may have finished right as the task was stopped or restarted
+                    if let TaskState::Restarting { .. } = task.state {
+                        if let Err(error) = task.run() {
+                            report_start_error(task, &error, &mut renderer, &mut sinks, interactive);
+                        }
+                        renderer.draw_tasks(&tasks)?;
+                    }
+                }
+                TaskMessageKind::Start => {
+                    let task = tasks.get_mut(id).unwrap();
+
+                    // the task may have been ended or started by hand while it was waiting
+                    if let TaskState::Pending = task.state {
+                        if let Err(error) = task.run() {
+                            report_start_error(task, &error, &mut renderer, &mut sinks, interactive);
+                        }
+                        renderer.draw_tasks(&tasks)?;
+                    }
+                }
+                TaskMessageKind::Ready => {
+                    let task = tasks.get_mut(id).unwrap();
+
+                    // the run the check belongs to may have just ended
+                    if let TaskState::Running { .. } = task.state {
+                        task.ready = true;
+                        renderer.draw_tasks(&tasks)?;
+                    }
+                }
+                TaskMessageKind::NotReady => {
+                    let task = tasks.get_mut(id).unwrap();
+
+                    if let TaskState::Running { .. } = task.state {
+                        task.not_ready = true;
+                        let secs = task.def.ready_timeout.unwrap_or_default().as_secs();
+                        task.logs.push(LogLine {
+                            text: format!("(not ready after {secs}s)").red().to_string(),
+                            stream: Stream::Stderr,
+                            kind: LogKind::Notice,
+                            level: None,
+                        });
+                        for sink in &mut sinks {
+                            sink.line(task, task.logs.last().unwrap());
+                        }
+                        renderer.draw_tasks(&tasks)?;
+                    }
+                }
+                TaskMessageKind::FileChanged => {
+                    let task = tasks.get_mut(id).unwrap();
+
+                    // tasks that haven't been started yet or are shutting down stay that way
+                    if !matches!(
+                        task.state,
+                        TaskState::Pending | TaskState::Stopping | TaskState::Stopped
+                    ) {
+                        if let Err(error) = task.force_restart(RestartReason::FileChanged) {
+                            report_start_error(task, &error, &mut renderer, &mut sinks, interactive);
+                        }
+                        renderer.draw_tasks(&tasks)?;
+                    }
                 }
             },
-            Some(Ok(event)) = events.next() => {
-                renderer.handle_input(event, &mut tasks);
+            Some(message) = control_rx.recv() => {
+                // no new tasks are started while shutting down
+                if renderer.is_quitting() {
+                    continue;
+                }
+
+                let task = message.and_then(|message| {
+                    let def = message.into_task_def(tasks.len(), options.name_from)?;
+                    let mut task = Task::new(def, tasks.len(), tx.clone());
+                    task.start_watching()?;
+                    task.collapsed = options.buffer_output;
+                    task.force_color = force_color;
+                    task.echo_command = options.echo_command;
+                    task.clear_env = options.env_inherit_none;
+                    Ok(task)
+                });
+
+                match task {
+                    Ok(mut task) => {
+                        // a task that couldn't be started is still shown, as failed
+                        let started = task.run();
+                        tasks.push(task);
+                        renderer.start_timer();
+                        if let Err(error) = started {
+                            let task = tasks.last().unwrap();
+                            report_start_error(task, &error, &mut renderer, &mut sinks, interactive);
+                        }
+                    }
+                    Err(error) => report_error(&mut renderer, interactive, &error),
+                }
+                renderer.draw_tasks(&tasks)?;
+            }
+            Some(Ok(event)) = async {
+                match &mut events {
+                    Some(events) => events.next().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                let is_space = matches!(
+                    event,
+                    Event::Key(KeyEvent { code: KeyCode::Char(' '), kind: KeyEventKind::Press, .. })
+                );
+                if is_space && tasks.iter().any(|task| matches!(task.state, TaskState::Pending)) {
+                    start_pending_tasks(&mut tasks, &mut renderer, &mut sinks, interactive);
+                    renderer.start_timer();
+                    // waiting for the tasks to be started doesn't count as being idle
+                    last_output_at = Instant::now();
+                } else {
+                    renderer.handle_input(event, &mut tasks);
+                }
+                renderer.draw_tasks(&tasks)?;
+            }
+            _ = poll_interval.tick() => {
+                if let Some(stats) = &mut stats {
+                    if stats_updated_at.is_none_or(|at| at.elapsed() >= STATS_INTERVAL) {
+                        stats.update(&mut tasks);
+                        stats_updated_at = Some(Instant::now());
+                    }
+                }
+                let since_check = line_rate_checked_at.elapsed();
+                if since_check >= LINE_RATE_INTERVAL {
+                    if let Some(threshold) = options.warn_lines {
+                        for task in &mut tasks {
+                            task.check_line_rate(threshold, since_check);
+                        }
+                    }
+                    line_rate_checked_at = Instant::now();
+                }
+                // tasks that are already being stopped aren't idle, just slow to exit
+                let idle = options
+                    .shutdown_on_idle
+                    .is_some_and(|idle| last_output_at.elapsed() >= idle);
+                if idle && !idled && !renderer.is_quitting() {
+                    idled = true;
+                    renderer.quit(&mut tasks);
+                    let notice = "no output for a while, stopping all tasks".to_owned();
+                    renderer.show_notice(notice.dark_grey());
+                }
+                renderer.draw_tasks(&tasks)?;
+            }
+            _ = &mut time_limit, if !timed_out => {
+                timed_out = true;
+                for task in tasks.iter_mut().filter(|task| !task.is_finished()) {
+                    task.timed_out = true;
+                }
+                renderer.quit(&mut tasks);
+                renderer.show_notice("time limit reached, stopping all tasks".to_owned().red());
+                renderer.draw_tasks(&tasks)?;
+            }
+            Ok(()) = interrupt_rx.recv() => {
+                // the first interrupt stops all tasks and waits for them to exit,
+                // a second one gives up on waiting
+                if interrupted {
+                    break;
+                }
+                interrupted = true;
+                renderer.quit(&mut tasks);
                 renderer.draw_tasks(&tasks)?;
             }
-            Ok(()) = interrupt_rx.recv() => break,
             else => break
         }
     }
 
-    renderer.leave_screen()?;
-    renderer.print_all_tasks(&tasks)?;
+    if interactive {
+        renderer.leave_screen()?;
+    }
+    if let Some(path) = &options.write_status {
+        write_status(path, options.label.as_deref(), &tasks)?;
+    }
+    if !plain {
+        match options.pager.then(spawn_pager).flatten() {
+            Some(mut pager) => {
+                renderer.redirect_output(Box::new(pager.stdin.take().unwrap()));
+                // the pager may be quit before it has read everything
+                let _ = renderer.print_all_tasks(&tasks);
+                renderer.redirect_output(Box::new(std::io::stdout()));
+                let _ = pager.wait();
+            }
+            None => renderer.print_all_tasks(&tasks)?,
+        }
+    }
+    for sink in &mut sinks {
+        sink.finish(renderer.elapsed());
+    }
+    if idled {
+        let note = format!(
+            "stopped all tasks after no output for {}",
+            format_elapsed(options.shutdown_on_idle.unwrap())
+        );
+        if color_enabled(&std::io::stderr()) {
+            eprintln!("{}", note.dark_grey());
+        } else {
+            eprintln!("{note}");
+        }
+    }
+    // tasks that were stopped by quitting, an interrupt or a limit didn't complete
+    match &options.on_complete {
+        Some(command) if !renderer.is_quitting() => run_on_complete(command, &tasks).await?,
+        _ => {}
+    }
+
+    if timed_out {
+        return Err(Error {
+            title: "time limit reached".into(),
+            message: format!(
+                "tasks that were still running after {} were stopped",
+                format_elapsed(options.time_limit.unwrap())
+            ),
+            ..Error::default()
+        });
+    }
+
+    // exit codes given with --success-codes already count as success here
+    if tasks.iter().any(Task::is_failed) {
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Whether a line looks like binary data rather than text, because more than `threshold` percent
+/// of its characters are control characters or bytes that weren't valid text. Escape sequences
+/// and whitespace are common in text, so they don't count.
+fn is_binary(line: &str, threshold: u8) -> bool {
+    let mut total = 0;
+    let mut unprintable = 0;
+    for c in line.chars() {
+        total += 1;
+        if c == char::REPLACEMENT_CHARACTER
+            || (c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x1b'))
+        {
+            unprintable += 1;
+        }
+    }
+    unprintable * 100 > total * threshold as usize
+}
+
+/// Strips escape sequences from a line of output and expands its tabs to spaces up to the next
+/// tab stop, counting wide characters as the columns they take up on screen.
+fn clean_line(line: &str, tab_width: usize) -> String {
+    let mut text = String::new();
+    let mut column = 0;
+    // stripping escape sequences also removes tabs, so each segment between them is stripped on its own
+    for (i, segment) in line.split('\t').enumerate() {
+        if i > 0 {
+            let spaces = tab_width - column % tab_width;
+            text.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        }
+        let segment = strip_ansi_escapes::strip_str(segment);
+        column += segment
+            .chars()
+            .map(|c| c.width().unwrap_or(0))
+            .sum::<usize>();
+        text.push_str(&segment);
+    }
+    text
+}
+
+/// Writes the status of all tasks to a file, see [`task_statuses`]. The run's label, if any,
+/// comes first as a `#` comment.
+fn write_status(path: &Path, label: Option<&str>, tasks: &[Task]) -> Result<(), Error> {
+    let label = label
+        .map(|label| format!("# {label}\n"))
+        .unwrap_or_default();
+
+    std::fs::write(path, label + &task_statuses(tasks)).map_err(|err| Error {
+        title: "failed to write status file".into(),
+        message: format!("{}: {err}", path.display()),
+        ..Error::default()
+    })
+}
+
+/// One line per task with its name and exit code, separated by a tab. Tasks killed by a signal
+/// get the signal name instead, and tasks that didn't exit on their own get their state.
+fn task_statuses(tasks: &[Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| {
+            let code = match &task.state {
+                TaskState::Exited(reason)
+                | TaskState::Restarting {
+                    exit_reason: reason,
+                    ..
+                } => match reason {
+                    TaskExitReason::Succeeded => "0".to_owned(),
+                    TaskExitReason::Failed(code) => code.to_string(),
+                    TaskExitReason::Killed(signal) => signal.to_string(),
+                },
+                TaskState::Pending => "pending".to_owned(),
+                TaskState::Stopping => "stopping".to_owned(),
+                TaskState::Stopped if task.timed_out => "timed-out".to_owned(),
+                TaskState::Stopped => "stopped".to_owned(),
+                _ => "running".to_owned(),
+            };
+            format!("{}\t{code}\n", task.def.name)
+        })
+        .collect()
+}
+
+/// Runs the `--on-complete` command in the shell, with its output going where congregation's
+/// does. How the tasks ended is passed in `FAILED_COUNT`, the number of tasks that failed or
+/// were killed, and `TASK_STATUSES`, in the format of [`task_statuses`].
+async fn run_on_complete(command: &str, tasks: &[Task]) -> Result<(), Error> {
+    let failed = tasks
+        .iter()
+        .filter(|task| match &task.state {
+            TaskState::Exited(reason) => *reason != TaskExitReason::Succeeded,
+            _ => false,
+        })
+        .count();
+
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd.exe", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let status = tokio::process::Command::new(shell)
+        .args([flag, command])
+        .env("FAILED_COUNT", failed.to_string())
+        .env("TASK_STATUSES", task_statuses(tasks))
+        .status()
+        .await
+        .map_err(|err| Error {
+            title: "failed to run --on-complete command".into(),
+            message: err.to_string(),
+            ..Error::default()
+        })?;
+
+    if status.success() {
+        return Ok(());
+    }
+    Err(Error {
+        title: "--on-complete command failed".into(),
+        message: match status.code() {
+            Some(code) => format!("'{command}' exited with code {code}"),
+            None => format!("'{command}' was killed"),
+        },
+        ..Error::default()
+    })
+}
+
+/// Starts the user's pager, or `less` if `$PAGER` isn't set, to show the output printed on exit.
+fn spawn_pager() -> Option<std::process::Child> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.is_empty())
+        .unwrap_or_else(|| "less".to_owned());
+
+    let mut command = if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd.exe");
+        command.args(["/C", &pager]);
+        command
+    } else {
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", &pager]);
+        command
+    };
+    let less = std::env::var("LESS").unwrap_or_default();
+    if let Some(less) = less_with_colors(&less) {
+        command.env("LESS", less);
+    }
+    command.stdin(std::process::Stdio::piped()).spawn().ok()
+}
+
+/// Adds `-R` to the user's `LESS` options unless they already pass colors through, since less
+/// shows escape sequences as text otherwise. Other options are kept as they are.
+fn less_with_colors(less: &str) -> Option<String> {
+    let passes_colors = less
+        .split_whitespace()
+        .any(|option| match option.strip_prefix("--") {
+            Some(long) => long.eq_ignore_ascii_case("raw-control-chars"),
+            // -r passes everything through, which includes colors
+            None => option.contains(['R', 'r']),
+        });
+    (!passes_colors).then(|| format!("{less} -R").trim_start().to_owned())
+}
+
+fn start_pending_tasks(
+    tasks: &mut [Task],
+    renderer: &mut Renderer,
+    sinks: &mut [Box<dyn Sink>],
+    interactive: bool,
+) {
+    for task in tasks {
+        if let (TaskState::Pending, None) = (&task.state, task.start_at) {
+            if let Err(error) = task.start() {
+                report_start_error(task, &error, renderer, sinks, interactive);
+            }
+        }
+    }
+}
+
+/// Reports a task that couldn't be started. It has failed right away, which the sinks are told
+/// about like any other exit.
+fn report_start_error(
+    task: &Task,
+    error: &Error,
+    renderer: &mut Renderer,
+    sinks: &mut [Box<dyn Sink>],
+    interactive: bool,
+) {
+    report_error(renderer, interactive, error);
+    for sink in sinks {
+        sink.status(task);
+    }
+}
 
-    Ok(())
+/// Reports an error that doesn't end the session, in the view if there is one.
+fn report_error(renderer: &mut Renderer, interactive: bool, error: &Error) {
+    if interactive {
+        renderer.show_error(error);
+    } else {
+        error.print();
+    }
 }
 
 #[tokio::main]
 async fn main() -> ExitCode {
     match run().await {
-        Ok(_) => ExitCode::SUCCESS,
+        Ok(code) => code,
         Err(error) => {
-            eprint!("{error}");
+            error.print();
             ExitCode::FAILURE
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_tab_indented_output() {
+        assert_eq!(clean_line("\tindented", 8), "        indented");
+        assert_eq!(clean_line("\t\tdeeper", 4), "        deeper");
+        assert_eq!(clean_line("ab\tc", 4), "ab  c");
+    }
+
+    #[test]
+    fn expands_tabs_after_wide_characters_by_their_width() {
+        // each of these takes up two columns, so the tab only has to fill up the last two
+        assert_eq!(clean_line("日本語\tx", 8), "日本語  x");
+        // combining characters take up no column of their own
+        assert_eq!(clean_line("e\u{301}\tx", 4), "e\u{301}   x");
+    }
+
+    #[test]
+    fn expands_tabs_around_escape_sequences() {
+        assert_eq!(clean_line("\x1b[31mred\x1b[0m\tx", 8), "red     x");
+    }
+}
@@ -0,0 +1,139 @@
+use crate::arg_parser::Options;
+use crate::color::color_enabled;
+use crate::renderer::format_elapsed;
+use crate::sink::{run_duration, Sink};
+use crate::task::{LogKind, LogLevel, LogLine, Stream, Task};
+use crate::theme::Theme;
+use crossterm::style::Stylize;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// Streams task output line by line, each line prefixed with the name of its task.
+pub struct PlainPrinter {
+    color: bool,
+    grep: Option<Regex>,
+    /// Prints only what tasks write to stderr, besides their exits.
+    only_errors: bool,
+    min_level: Option<LogLevel>,
+    prefix_format: String,
+    /// Holds back each task's output until it exits, so it's printed in one piece.
+    buffer_output: bool,
+    /// How many log lines of each task have been printed, by task id.
+    printed_logs: HashMap<usize, usize>,
+    theme: Theme,
+    raw: bool,
+    /// Whether status lines say how long the run took.
+    annotate_exit: bool,
+}
+
+impl PlainPrinter {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            color: color_enabled(&std::io::stdout()),
+            grep: options.grep.clone(),
+            only_errors: options.prefix_only_errors,
+            min_level: options.min_level,
+            prefix_format: options.prefix_format.clone(),
+            buffer_output: options.buffer_output,
+            printed_logs: HashMap::new(),
+            theme: options.theme.clone(),
+            raw: options.raw,
+            annotate_exit: options.annotate_exit,
+        }
+    }
+
+    fn prefix(&self, task: &Task) -> String {
+        let elapsed = task
+            .started_at
+            .map(|started_at| started_at.elapsed())
+            .unwrap_or_default();
+
+        let mut prefix = self
+            .prefix_format
+            .replace("{name}", &task.def.name)
+            .replace("{elapsed}", &format!("{:.1}s", elapsed.as_secs_f64()))
+            .bold();
+        prefix.style_mut().foreground_color = task.def.color;
+        prefix.to_string()
+    }
+
+    fn print(&self, text: String) {
+        if self.color {
+            Self::write(&text);
+        } else {
+            Self::write(&strip_ansi_escapes::strip_str(text));
+        }
+    }
+
+    fn write(text: &str) {
+        let _ = writeln!(std::io::stdout().lock(), "{text}");
+    }
+
+    fn write_line(&self, task: &Task, log: &LogLine) {
+        // lines of a previous run only make sense next to the current one in the interactive view
+        if log.kind == LogKind::Removed {
+            return;
+        }
+        if self.only_errors && log.stream != Stream::Stderr {
+            return;
+        }
+        let line = &log.text;
+        if self.grep.as_ref().is_some_and(|grep| !grep.is_match(line)) {
+            return;
+        }
+        if let (Some(min), Some(level)) = (self.min_level, log.level) {
+            if level < min {
+                return;
+            }
+        }
+
+        if self.raw && !self.color {
+            // only the prefix loses its colors, the line is written as the task printed it
+            let prefix = strip_ansi_escapes::strip_str(self.prefix(task));
+            Self::write(&format!("{prefix} {line}"));
+        } else {
+            self.print(format!("{} {line}", self.prefix(task)));
+        }
+    }
+}
+
+impl Sink for PlainPrinter {
+    fn line(&mut self, task: &Task, log: &LogLine) {
+        if !self.buffer_output {
+            self.write_line(task, log);
+        }
+    }
+
+    fn status(&mut self, task: &Task) {
+        if self.buffer_output {
+            let printed = self.printed_logs.insert(task.id, task.logs.len());
+            for log in &task.logs[printed.unwrap_or(0)..] {
+                self.write_line(task, log);
+            }
+        }
+
+        let duration = if self.annotate_exit {
+            run_duration(task).dark_grey().to_string()
+        } else {
+            String::new()
+        };
+        self.print(format!(
+            "{} {}{duration}",
+            self.prefix(task),
+            self.theme.task_state(task)
+        ));
+    }
+
+    /// Prints how long the whole session took.
+    fn finish(&mut self, elapsed: Option<Duration>) {
+        if let Some(elapsed) = elapsed {
+            self.print(
+                format!("finished in {}", format_elapsed(elapsed))
+                    .dark_grey()
+                    .to_string(),
+            );
+        }
+    }
+}
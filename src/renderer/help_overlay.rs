@@ -1,12 +1,12 @@
-use std::io::Stdout;
+use std::io::Write;
 
 use crossterm::{
     cursor, queue,
     style::{self, Stylize},
-    terminal,
 };
+use unicode_width::UnicodeWidthStr;
 
-pub fn print_key(stdout: &mut Stdout, key: &str, name: &str) -> std::io::Result<()> {
+pub fn print_key(stdout: &mut impl Write, key: &str, name: &str) -> std::io::Result<()> {
     queue!(
         stdout,
         style::Print(format!(" {key} ").black().on_dark_grey()),
@@ -16,9 +16,7 @@ pub fn print_key(stdout: &mut Stdout, key: &str, name: &str) -> std::io::Result<
     )
 }
 
-pub fn render_help_overlay(stdout: &mut Stdout) -> std::io::Result<()> {
-    let (w, h) = terminal::size()?;
-
+pub fn render_help_overlay(stdout: &mut impl Write, w: u16, h: u16) -> std::io::Result<()> {
     const HELP_WIDTH: i32 = 48;
 
     enum HelpLine {
@@ -29,24 +27,52 @@ pub fn render_help_overlay(stdout: &mut Stdout) -> std::io::Result<()> {
         HelpLine::Text(""),
         HelpLine::Text("NAVIGATION"),
         HelpLine::Key(("←↓↑→/hjkl", "move cursor")),
+        HelpLine::Key(("<count>j", "move by count lines, e.g. 10j")),
         HelpLine::Key(("u", "pgup")),
         HelpLine::Key(("d", "pgdown")),
-        HelpLine::Key(("ctrl+↑/ctrl+k", "jump to next task")),
-        HelpLine::Key(("ctrl+↓/ctrl+j", "jump to previous task")),
+        HelpLine::Key(("ctrl+u/ctrl+d", "half page up/down")),
+        HelpLine::Key(("ctrl+↑/ctrl+k", "jump to previous task")),
+        HelpLine::Key(("ctrl+↓/ctrl+j", "jump to next task")),
+        HelpLine::Key(("[/]", "scroll to previous/next task")),
+        HelpLine::Key(("</>", "previous/next page of tasks")),
+        HelpLine::Key(("i", "toggle one stream of all output")),
+        HelpLine::Key(("a", "toggle failed/running tasks first")),
+        HelpLine::Key(("t", "cycle color schemes")),
+        HelpLine::Key(("/", "search output (↑↓ for history)")),
+        HelpLine::Key(("n/N", "next/previous match or mark")),
+        HelpLine::Key(("ctrl+n/ctrl+p", "next/previous mark or task")),
         HelpLine::Key(("q", "quit")),
         HelpLine::Text(""),
         HelpLine::Text("MANAGING TASKS"),
-        HelpLine::Key(("space/enter", "collapse/expand task")),
+        HelpLine::Key(("space/enter", "collapse/expand task or group")),
         HelpLine::Key(("x", "end task")),
         HelpLine::Key(("r", "restart task")),
+        HelpLine::Key(("F", "rerun failed tasks")),
+        HelpLine::Key(("R", "restart all tasks")),
+        HelpLine::Key(("p", "pause/resume all tasks")),
+        HelpLine::Key(("s", "split/merge stderr")),
+        HelpLine::Key(("e", "hide/show stderr")),
+        HelpLine::Key(("w", "wrap/unwrap task lines")),
+        HelpLine::Key(("v", "cycle minimum log level")),
+        HelpLine::Key(("m", "mark the end of task output")),
+        HelpLine::Key(("o", "save task output to a file")),
         HelpLine::Text(""),
     ];
+    // on short terminals the lines that don't fit between the borders are left out
+    let fitting = (h as usize).saturating_sub(2).min(lines.len());
+    let truncated = fitting < lines.len();
+    let mut lines: Vec<HelpLine> = lines.into_iter().take(fitting).collect();
+    if truncated {
+        if let Some(last) = lines.last_mut() {
+            *last = HelpLine::Text("… (enlarge the terminal to see all keys)");
+        }
+    }
     let help_height = (lines.len() + 2) as i32;
 
-    let help_x = w as i32 / 2 - HELP_WIDTH / 2;
-    let help_y = h as i32 / 2 - help_height / 2;
+    let help_x = (w as i32 / 2 - HELP_WIDTH / 2).max(0) as u16;
+    let help_y = (h as i32 / 2 - help_height / 2).max(0) as u16;
 
-    queue!(stdout, cursor::MoveTo(help_x as u16, help_y as u16),)?;
+    queue!(stdout, cursor::MoveTo(help_x, help_y))?;
     queue!(
         stdout,
         style::Print("┌"),
@@ -54,18 +80,16 @@ pub fn render_help_overlay(stdout: &mut Stdout) -> std::io::Result<()> {
         style::Print("┐"),
     )?;
 
-    queue!(
-        stdout,
-        style::Print("\n"),
-        cursor::MoveToColumn(help_x as u16)
-    )?;
-
-    for line in lines {
+    for (row, line) in lines.into_iter().enumerate() {
         let width = match line {
-            HelpLine::Key((name, action)) => name.chars().count() + 4 + action.chars().count(),
-            HelpLine::Text(text) => text.len(),
+            HelpLine::Key((name, action)) => name.width() + 4 + action.width(),
+            HelpLine::Text(text) => text.width(),
         } as i32;
-        queue!(stdout, style::Print("│  "))?;
+        queue!(
+            stdout,
+            cursor::MoveTo(help_x, help_y + row as u16 + 1),
+            style::Print("│  ")
+        )?;
         match line {
             HelpLine::Key((name, action)) => {
                 print_key(stdout, name, action)?;
@@ -76,13 +100,12 @@ pub fn render_help_overlay(stdout: &mut Stdout) -> std::io::Result<()> {
             stdout,
             style::Print(" ".repeat((HELP_WIDTH - width - 4) as usize)),
             style::Print("│"),
-            style::Print("\n"),
-            cursor::MoveToColumn(help_x as u16)
         )?;
     }
 
     queue!(
         stdout,
+        cursor::MoveTo(help_x, help_y + help_height as u16 - 1),
         style::Print("└"),
         style::Print("─".repeat((HELP_WIDTH - 2) as usize)),
         style::Print("┘"),
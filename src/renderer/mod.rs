@@ -1,14 +1,55 @@
-use crate::task::Task;
+use crate::arg_parser::Options;
+use crate::color::color_enabled;
+use crate::diagnostics::Error;
+use crate::task::{
+    LogKind, LogLevel, LogLine, RestartReason, Stream, Task, TaskExitReason, TaskState,
+};
+use crate::theme::{Palette, Theme};
 use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::style::{Color, StyledContent, Stylize};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, ClearType};
 use crossterm::{cursor, execute, queue, style, terminal, QueueableCommand};
-use std::io::{Stdout, Write};
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 mod help_overlay;
 
 const LOG_PREFIX: &str = "│ ";
 const STATUS_PREFIX: &str = "└ ";
+const SECTION_PREFIX: &str = "├ ";
+const MARK_PREFIX: &str = "◆ ";
+const MARK: &str = "── mark ──";
+
+/// How many searches are remembered.
+const MAX_SEARCH_HISTORY: usize = 50;
+
+/// How long a second press confirms quitting with `--confirm-quit`.
+const QUIT_CONFIRM_WINDOW: Duration = Duration::from_secs(2);
+
+/// The order of tasks in the output printed on exit, as chosen with `--summary-sort`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SummarySort {
+    /// The order the tasks are shown in while running.
+    Input,
+    /// Failed tasks first, then those that succeeded, then those that didn't finish.
+    Status,
+}
+
+impl FromStr for SummarySort {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "input" => Ok(Self::Input),
+            "status" => Ok(Self::Status),
+            _ => Err(()),
+        }
+    }
+}
 
 #[derive(PartialEq)]
 enum Overlay {
@@ -16,7 +57,7 @@ enum Overlay {
 }
 
 pub struct Renderer {
-    stdout: Stdout,
+    stdout: Box<dyn Write>,
     viewport_width: usize,
     viewport_height: usize,
     scroll_x: usize,
@@ -24,10 +65,84 @@ pub struct Renderer {
     cursor_x: usize,
     cursor_y: usize,
     selected_task_id: usize,
+    /// The group whose header is under the cursor, if any.
+    selected_group: Option<String>,
     line_count: usize,
     cursor_line_length: usize,
     in_screen: bool,
     overlays: Vec<Overlay>,
+    separate_stderr: bool,
+    colorize_lines: bool,
+    color: bool,
+    follow_threshold: usize,
+    scroll_step: usize,
+    page_step: Option<usize>,
+    notice: Option<StyledContent<String>>,
+    quitting: bool,
+    label: Option<String>,
+    theme: Theme,
+    /// The search being typed after pressing `/`.
+    search_input: Option<String>,
+    /// The last confirmed search, whose matches n/N jump between.
+    search: Option<String>,
+    /// Previous searches, the most recent last.
+    search_history: Vec<String>,
+    /// The search from the history shown in the prompt while going through it with up and down.
+    history_position: Option<usize>,
+    search_history_file: Option<PathBuf>,
+    /// Whether output is drawn exactly as the task printed it, escape sequences and all.
+    raw: bool,
+    stats: bool,
+    /// Log lines with a lower level are hidden.
+    min_level: Option<LogLevel>,
+    /// When the first task was started, for the session timer.
+    started_at: Option<Instant>,
+    confirm_quit: bool,
+    /// Whether task headers start with the task's number.
+    index_headers: bool,
+    /// Whether tasks can't be ended or restarted, because they're from a replay.
+    read_only: bool,
+    /// When quitting was last held off to ask for confirmation.
+    quit_requested_at: Option<Instant>,
+    /// The rows drawn in the last frame, so that only the ones that changed are drawn again.
+    frame: Vec<Vec<u8>>,
+    frame_width: usize,
+    /// Whether the output of all tasks is shown as one stream, in the order it arrived, instead
+    /// of grouped by task.
+    interleave: bool,
+    /// Whether task statuses show how many lines and bytes the task printed.
+    counts: bool,
+    /// Whether failed tasks are shown first, then running ones, then all others.
+    auto_reorder: bool,
+    /// Whether lines written to stderr are left out of the interactive view.
+    hide_stderr: bool,
+    /// Every log line so far as (task id, index in its logs), in the order they arrived.
+    arrival_order: Vec<(usize, usize)>,
+    /// How many log lines of each task are in `arrival_order`, by task id.
+    ordered_logs: Vec<usize>,
+    /// Each task's `diff_count` when its lines in `arrival_order` were last remapped.
+    diff_counts: Vec<usize>,
+    /// Width of the longest task name, which task names in the stream are padded to.
+    source_width: usize,
+    /// How many lines of each task the output printed on exit is limited to.
+    tail_on_exit: Option<usize>,
+    summary_sort: SummarySort,
+    /// A count typed before a movement key, like the 10 in `10j`, which repeats the movement.
+    pending_count: Option<usize>,
+    /// How many tasks the interactive view shows at a time, if it's paged.
+    tasks_per_page: Option<usize>,
+    /// The page of tasks shown, counting from 0.
+    task_page: usize,
+    /// Whether the interactive view is drawn on the alternate screen, which is gone after exit,
+    /// instead of the terminal's main one.
+    alt_screen: bool,
+    /// Whether the footer shows hints for the most useful keys.
+    key_hints: bool,
+    /// The active color scheme, as an index into [`Palette::ALL`].
+    palette_index: usize,
+    /// The width and height the view is drawn with instead of the terminal's, for a layout that
+    /// doesn't depend on the terminal, e.g. in recordings.
+    size_override: (Option<u16>, Option<u16>),
 }
 
 #[derive(Clone)]
@@ -37,9 +152,31 @@ enum Line<'a> {
         name: &'a str,
         color: Option<Color>,
         collapsed: bool,
+        exit_reason: Option<TaskExitReason>,
+        line_rate_warning: Option<usize>,
+    },
+    Group {
+        id: usize,
+        name: &'a str,
+        status: StyledContent<String>,
     },
     TaskStatus(usize, StyledContent<String>),
-    Log(usize, &'a str),
+    Section(usize, &'static str),
+    Log {
+        id: usize,
+        text: &'a str,
+        tint: Option<Color>,
+        /// A dim shade of the task's color for the gutter, so it shows which task a line is from.
+        gutter: Option<Color>,
+        wrapped: bool,
+        kind: LogKind,
+        /// The name and color of the task, shown in front of the line when the output of all
+        /// tasks is one stream.
+        source: Option<(&'a str, Option<Color>)>,
+    },
+    Mark(usize),
+    /// How many lines of a task were left out at the start of its output.
+    Omitted(usize, usize),
     Empty,
 }
 
@@ -48,44 +185,150 @@ impl Line<'_> {
         match self {
             Line::TaskName { id, .. } => Some(*id),
             Line::TaskStatus(id, _) => Some(*id),
-            Line::Log(id, _) => Some(*id),
+            Line::Section(id, _) => Some(*id),
+            Line::Log { id, .. } => Some(*id),
+            Line::Mark(id) => Some(*id),
+            Line::Omitted(id, _) => Some(*id),
+            Line::Group { id, .. } => Some(*id),
             Line::Empty => None,
         }
     }
 }
 
 impl Renderer {
-    pub fn new() -> Self {
+    pub fn new(options: &Options) -> Self {
+        Self::with_output(options, Box::new(std::io::stdout()))
+    }
+
+    /// Creates a renderer that draws into the given output instead of stdout. Whether to use
+    /// colors is still decided by stdout, which the output usually ends up on.
+    pub fn with_output(options: &Options, out: Box<dyn Write>) -> Self {
         Self {
-            stdout: std::io::stdout(),
+            stdout: out,
             scroll_x: 0,
             scroll_y: 0,
             cursor_x: 0,
             cursor_y: 0,
             selected_task_id: 0,
+            selected_group: None,
             viewport_width: 0,
             viewport_height: 0,
             line_count: 0,
             cursor_line_length: 0,
             in_screen: false,
             overlays: vec![],
+            separate_stderr: options.separate_stderr,
+            colorize_lines: options.colorize_lines,
+            color: color_enabled(&std::io::stdout()),
+            follow_threshold: options.follow_threshold,
+            scroll_step: options.scroll_step,
+            page_step: options.page_step,
+            notice: None,
+            quitting: false,
+            label: options.label.clone(),
+            theme: options.theme.clone(),
+            search_input: None,
+            search: None,
+            search_history: options
+                .search_history
+                .as_ref()
+                .map(read_search_history)
+                .unwrap_or_default(),
+            history_position: None,
+            search_history_file: options.search_history.clone(),
+            raw: options.raw,
+            stats: options.stats,
+            min_level: options.min_level,
+            started_at: None,
+            confirm_quit: options.confirm_quit,
+            index_headers: options.index_headers,
+            read_only: options.replay.is_some(),
+            quit_requested_at: None,
+            frame: vec![],
+            frame_width: 0,
+            interleave: options.interleave,
+            auto_reorder: options.auto_reorder,
+            hide_stderr: false,
+            counts: options.counts,
+            arrival_order: Vec::new(),
+            ordered_logs: Vec::new(),
+            diff_counts: Vec::new(),
+            source_width: 0,
+            palette_index: 0,
+            pending_count: None,
+            tasks_per_page: options.tasks_per_page,
+            task_page: 0,
+            alt_screen: options.alt_screen,
+            key_hints: options.key_hints,
+            tail_on_exit: options.tail_on_exit,
+            summary_sort: options.summary_sort,
+            size_override: (options.width, options.height),
         }
     }
 
     pub fn enter_screen(&mut self) -> std::io::Result<()> {
-        execute!(self.stdout, terminal::EnterAlternateScreen)?;
-        enable_raw_mode()?;
+        if self.alt_screen {
+            execute!(self.stdout, terminal::EnterAlternateScreen)?;
+        } else {
+            // push what's on the screen into the scrollback, so the view starts on an empty one
+            let (_, height) = self.size()?;
+            execute!(self.stdout, style::Print("\n".repeat(height as usize)))?;
+        }
+        if let Some(label) = &self.label {
+            execute!(self.stdout, terminal::SetTitle(label))?;
+        }
+        if let Err(err) = enable_raw_mode() {
+            if self.alt_screen {
+                let _ = execute!(self.stdout, terminal::LeaveAlternateScreen);
+            }
+            return Err(err);
+        }
         self.in_screen = true;
+        self.frame.clear();
         Ok(())
     }
 
     pub fn leave_screen(&mut self) -> std::io::Result<()> {
         disable_raw_mode()?;
-        execute!(self.stdout, terminal::LeaveAlternateScreen)?;
+        if self.alt_screen {
+            execute!(self.stdout, terminal::LeaveAlternateScreen)?;
+        } else {
+            // the output printed on exit takes the place of the last frame
+            self.clear_rows()?;
+            execute!(self.stdout, cursor::MoveTo(0, 0))?;
+        }
         self.in_screen = false;
         Ok(())
     }
 
+    /// The size of the view, which is the terminal's unless `--width` or `--height` override it.
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        Ok(match self.size_override {
+            (Some(width), Some(height)) => (width, height),
+            (width, height) => {
+                let (terminal_width, terminal_height) = terminal::size()?;
+                (
+                    width.unwrap_or(terminal_width),
+                    height.unwrap_or(terminal_height),
+                )
+            }
+        })
+    }
+
+    /// Clears the screen row by row. Clearing all of it at once makes some terminals keep a copy
+    /// in the scrollback, which would fill it with old frames without the alternate screen.
+    fn clear_rows(&mut self) -> std::io::Result<()> {
+        let (_, height) = self.size()?;
+        for row in 0..height {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, row),
+                terminal::Clear(ClearType::CurrentLine)
+            )?;
+        }
+        Ok(())
+    }
+
     fn set_cursor_x(&mut self, position: usize) {
         self.cursor_x = self.cursor_line_length.min(position);
         if self.cursor_x >= self.viewport_width + self.scroll_x {
@@ -100,7 +343,7 @@ impl Renderer {
 
     fn set_cursor_y(&mut self, position: usize) {
         // exclude the status bar
-        let actual_viewport_height = self.viewport_height - 1;
+        let actual_viewport_height = self.viewport_height.saturating_sub(1);
 
         self.cursor_y = self.line_count.min(position);
         if self.cursor_y >= actual_viewport_height + self.scroll_y {
@@ -111,16 +354,20 @@ impl Renderer {
         }
     }
 
-    fn page_up(&mut self) {
-        self.set_cursor_y(self.cursor_y.saturating_sub(self.viewport_height));
+    fn page_size(&self) -> usize {
+        self.page_step.unwrap_or(self.viewport_height)
     }
 
-    fn page_down(&mut self) {
-        self.set_cursor_y(self.cursor_y + self.viewport_height);
+    fn page_up(&mut self, lines: usize) {
+        self.set_cursor_y(self.cursor_y.saturating_sub(lines));
+    }
+
+    fn page_down(&mut self, lines: usize) {
+        self.set_cursor_y(self.cursor_y + lines);
     }
 
     fn jump_to_task_name(&mut self, tasks: &[Task], task_id: usize) {
-        for (idx, line) in self.render(tasks).enumerate() {
+        for (idx, line) in self.render(tasks).into_iter().enumerate() {
             if let Line::TaskName { id, .. } = line {
                 if id == task_id {
                     self.set_cursor_y(idx);
@@ -130,46 +377,388 @@ impl Renderer {
         }
     }
 
+    fn jump_to_adjacent_task(&mut self, tasks: &[Task], forward: bool) {
+        let order = self.display_order(tasks);
+        let Some(position) = order
+            .iter()
+            .position(|task| task.id == self.selected_task_id)
+        else {
+            return;
+        };
+
+        let target = if forward {
+            order.get(position + 1)
+        } else {
+            order.get(position.saturating_sub(1))
+        };
+        if let Some(task) = target {
+            self.jump_to_task_name(tasks, task.id);
+        }
+    }
+
+    fn scroll_to_task_header(&mut self, tasks: &[Task], forward: bool) {
+        let mut headers = self
+            .render(tasks)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, line)| matches!(line, Line::TaskName { .. }))
+            .map(|(idx, _)| idx);
+
+        let target = if forward {
+            headers.find(|idx| *idx > self.scroll_y)
+        } else {
+            headers.rfind(|idx| *idx < self.scroll_y)
+        };
+
+        if let Some(idx) = target {
+            self.scroll_to_top(idx);
+        }
+    }
+
+    /// Scrolls a line to the top of the view and moves the cursor onto it.
+    fn scroll_to_top(&mut self, idx: usize) {
+        // keep the last line at the bottom of the viewport instead of scrolling past it
+        let max_scroll =
+            (self.line_count + 1).saturating_sub(self.viewport_height.saturating_sub(1));
+        self.scroll_y = idx.min(max_scroll);
+        self.set_cursor_y(idx);
+    }
+
+    /// Jumps to the next or previous mark or task header, whichever comes first, so marks can be
+    /// used as bookmarks to skip through long output.
+    fn jump_to_bookmark(&mut self, tasks: &[Task], forward: bool) {
+        let mut bookmarks = self
+            .render(tasks)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, line)| matches!(line, Line::Mark(_) | Line::TaskName { .. }))
+            .map(|(idx, _)| idx);
+
+        let target = if forward {
+            bookmarks.find(|idx| *idx > self.cursor_y)
+        } else {
+            bookmarks.rfind(|idx| *idx < self.cursor_y)
+        };
+
+        if let Some(idx) = target {
+            self.scroll_to_top(idx);
+        }
+    }
+
+    fn jump_to_mark(&mut self, tasks: &[Task], forward: bool) {
+        let mut marks = self
+            .render(tasks)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, line)| matches!(line, Line::Mark(_)))
+            .map(|(idx, _)| idx);
+
+        let target = if forward {
+            marks.find(|idx| *idx > self.cursor_y)
+        } else {
+            marks.rfind(|idx| *idx < self.cursor_y)
+        };
+
+        if let Some(idx) = target {
+            self.set_cursor_y(idx);
+        }
+    }
+
+    /// The positions of all search matches as (line, column) pairs, in display order.
+    fn search_matches(&self, tasks: &[Task], query: &str) -> Vec<(usize, usize)> {
+        let query = query.to_lowercase();
+        self.render(tasks)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, line)| match line {
+                Line::Log { text, .. } => {
+                    let text = text.to_lowercase();
+                    let column = text.find(&query)?;
+                    Some((idx, text[..column].width()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Moves the cursor to the next or previous search match, scrolling horizontally as well
+    /// when the match is outside of the viewport.
+    fn jump_to_match(&mut self, tasks: &[Task], forward: bool) {
+        let Some(query) = self.search.clone() else {
+            return;
+        };
+
+        let prefix_width = self.log_prefix_width();
+        let current = (self.cursor_y, self.cursor_x.saturating_sub(prefix_width));
+        // like in a pager, the search wraps around at either end
+        let matches = self.search_matches(tasks, &query);
+        let target = if forward {
+            matches
+                .iter()
+                .find(|position| **position > current)
+                .or(matches.first())
+        } else {
+            matches
+                .iter()
+                .rfind(|position| **position < current)
+                .or(matches.last())
+        };
+
+        if let Some(&(idx, column)) = target {
+            self.set_cursor_y(idx);
+            // the cursor is clamped to the length of the line it was on before the jump
+            let match_end = prefix_width + column + query.width();
+            self.cursor_line_length = self.cursor_line_length.max(match_end + 1);
+            // scroll the end of the match into view first, past the clipping marker, so all of
+            // it is visible once the cursor is on its start
+            self.set_cursor_x(match_end + 1);
+            self.set_cursor_x(prefix_width + column);
+        } else {
+            self.show_notice(format!("no matches for '{query}'").red());
+        }
+    }
+
+    fn handle_search_input(&mut self, code: KeyCode, tasks: &[Task]) {
+        let Some(input) = &mut self.search_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Char(c) => {
+                input.push(c);
+                self.history_position = None;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                self.history_position = None;
+            }
+            KeyCode::Up if !self.search_history.is_empty() => {
+                let position = match self.history_position {
+                    Some(position) => position.saturating_sub(1),
+                    None => self.search_history.len() - 1,
+                };
+                *input = self.search_history[position].clone();
+                self.history_position = Some(position);
+            }
+            KeyCode::Down => match self.history_position {
+                Some(position) if position + 1 < self.search_history.len() => {
+                    *input = self.search_history[position + 1].clone();
+                    self.history_position = Some(position + 1);
+                }
+                // going past the most recent search brings back an empty prompt
+                _ => {
+                    input.clear();
+                    self.history_position = None;
+                }
+            },
+            KeyCode::Enter => {
+                let query = self.search_input.take().unwrap();
+                self.history_position = None;
+                if !query.is_empty() {
+                    self.add_to_search_history(&query);
+                }
+                self.search = (!query.is_empty()).then_some(query);
+                self.jump_to_match(tasks, true);
+            }
+            KeyCode::Esc => {
+                self.search_input = None;
+                self.history_position = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Remembers a search as the most recent one, saving the history to the history file if
+    /// there is one.
+    fn add_to_search_history(&mut self, query: &str) {
+        self.search_history.retain(|search| search != query);
+        self.search_history.push(query.to_owned());
+        if self.search_history.len() > MAX_SEARCH_HISTORY {
+            self.search_history.remove(0);
+        }
+
+        if let Some(path) = &self.search_history_file {
+            let history: String = self
+                .search_history
+                .iter()
+                .map(|search| format!("{search}\n"))
+                .collect();
+            if let Err(err) = std::fs::write(path, history) {
+                self.show_notice(format!("failed to save search history: {err}").red());
+            }
+        }
+    }
+
     pub fn quit(&mut self, tasks: &mut [Task]) {
+        self.quitting = true;
         for task in tasks {
             task.end_gracefully();
         }
     }
 
+    /// Quits, unless tasks are still running with `--confirm-quit` and this isn't the second press.
+    fn request_quit(&mut self, tasks: &mut [Task], confirmed: bool) {
+        let running = tasks.iter().any(|task| {
+            matches!(
+                task.state,
+                TaskState::Running { .. }
+                    | TaskState::Restarting { .. }
+                    | TaskState::ForceRestarting(_)
+            )
+        });
+        if self.confirm_quit && running && !confirmed && !self.quitting {
+            self.quit_requested_at = Some(Instant::now());
+            self.show_notice(
+                "tasks are still running, press again to quit"
+                    .to_owned()
+                    .yellow(),
+            );
+            return;
+        }
+        self.quit(tasks);
+    }
+
+    pub fn is_quitting(&self) -> bool {
+        self.quitting
+    }
+
+    /// Starts the session timer shown in the footer, unless it's already running.
+    pub fn start_timer(&mut self) {
+        self.started_at.get_or_insert_with(Instant::now);
+    }
+
+    /// Time since the first task was started, if any was.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.started_at.map(|started_at| started_at.elapsed())
+    }
+
+    /// Pauses all running tasks, or resumes them if they're all paused already.
+    fn toggle_pause(&mut self, tasks: &mut [Task]) {
+        let running = |task: &&mut Task| matches!(task.state, TaskState::Running { .. });
+        let pause = tasks.iter_mut().filter(running).any(|task| !task.paused);
+
+        // every task is signalled, even after one of them failed
+        let results: Vec<_> = tasks
+            .iter_mut()
+            .filter(running)
+            .map(|task| if pause { task.pause() } else { task.resume() })
+            .collect();
+        if let Some(Err(err)) = results.into_iter().find(Result::is_err) {
+            let action = if pause { "pause" } else { "resume" };
+            self.show_notice(format!("failed to {action} tasks: {err}").red());
+        } else if pause {
+            self.show_notice("tasks paused, press p to resume them".to_owned().yellow());
+        } else {
+            self.show_notice("tasks resumed".to_owned().dark_grey());
+        }
+    }
+
+    fn palette(&self) -> Palette {
+        Palette::ALL[self.palette_index]
+    }
+
+    /// Moves the cursor to a task's header, e.g. to draw attention to it.
+    pub fn focus_task(&mut self, tasks: &[Task], task_id: usize) {
+        self.jump_to_task_name(tasks, task_id);
+    }
+
+    /// Shows a message in the footer until the next key is pressed.
+    pub fn show_notice(&mut self, notice: StyledContent<String>) {
+        self.notice = Some(notice);
+    }
+
+    /// Shows an error that doesn't end the session as a notice.
+    pub fn show_error(&mut self, error: &Error) {
+        self.show_notice(format!("{}: {}", error.title, error.message).red());
+    }
+
     pub fn handle_input(&mut self, event: Event, tasks: &mut [Task]) {
+        let mut quit_confirmed = false;
+        let mut count = None;
+        if let Event::Key(event) = event {
+            self.notice = None;
+            quit_confirmed = self
+                .quit_requested_at
+                .take()
+                .is_some_and(|at| at.elapsed() < QUIT_CONFIRM_WINDOW);
+            // the count only applies to the key right after it
+            if event.kind == KeyEventKind::Press {
+                count = self.pending_count.take();
+            }
+        }
+        let repeat = count.unwrap_or(1);
+
         match event {
             Event::Key(event) if event.kind == KeyEventKind::Press => match event.code {
                 KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.quit(tasks);
+                    self.request_quit(tasks, quit_confirmed);
+                }
+                code if self.search_input.is_some() => self.handle_search_input(code, tasks),
+                KeyCode::Char('/') => self.search_input = Some(String::new()),
+                // a leading 0 goes to the start of the line instead
+                KeyCode::Char(digit @ '0'..='9') if count.is_some() || digit != '0' => {
+                    let digit = digit.to_digit(10).unwrap() as usize;
+                    self.pending_count =
+                        Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                }
+                KeyCode::Char('u') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.page_up(self.page_size() / 2)
+                }
+                KeyCode::Char('d') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.page_down(self.page_size() / 2)
+                }
+                KeyCode::Char('n') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.jump_to_bookmark(tasks, true)
                 }
-                KeyCode::Char('u') | KeyCode::PageUp => self.page_up(),
-                KeyCode::Char('d') | KeyCode::PageDown => self.page_down(),
+                KeyCode::Char('p') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.jump_to_bookmark(tasks, false)
+                }
+                KeyCode::Char('u') | KeyCode::PageUp => self.page_up(self.page_size()),
+                KeyCode::Char('d') | KeyCode::PageDown => self.page_down(self.page_size()),
                 KeyCode::Up | KeyCode::Char('k')
                     if event.modifiers.contains(KeyModifiers::CONTROL) =>
                 {
-                    self.jump_to_task_name(tasks, self.selected_task_id.saturating_sub(1))
+                    self.jump_to_adjacent_task(tasks, false)
                 }
                 KeyCode::Down | KeyCode::Char('j')
                     if event.modifiers.contains(KeyModifiers::CONTROL) =>
                 {
-                    self.jump_to_task_name(tasks, self.selected_task_id + 1)
+                    self.jump_to_adjacent_task(tasks, true)
                 }
+                KeyCode::Char('<') => self.change_task_page(tasks, false),
+                KeyCode::Char('>') => self.change_task_page(tasks, true),
+                KeyCode::Char('[') => self.scroll_to_task_header(tasks, false),
+                KeyCode::Char(']') => self.scroll_to_task_header(tasks, true),
                 KeyCode::Left if event.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.set_cursor_x(0)
                 }
                 KeyCode::Right if event.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.set_cursor_x(self.cursor_line_length)
                 }
-                KeyCode::Up | KeyCode::Char('k') => {
-                    self.set_cursor_y(self.cursor_y.saturating_sub(1))
-                }
-                KeyCode::Down | KeyCode::Char('j') => self.set_cursor_y(self.cursor_y + 1),
+                KeyCode::Up | KeyCode::Char('k') => self.set_cursor_y(
+                    self.cursor_y
+                        .saturating_sub(self.scroll_step.saturating_mul(repeat)),
+                ),
+                KeyCode::Down | KeyCode::Char('j') => self.set_cursor_y(
+                    self.cursor_y
+                        .saturating_add(self.scroll_step.saturating_mul(repeat)),
+                ),
                 KeyCode::Left | KeyCode::Char('h') => {
-                    self.set_cursor_x(self.cursor_x.saturating_sub(1));
+                    self.set_cursor_x(self.cursor_x.saturating_sub(repeat));
+                }
+                KeyCode::Right | KeyCode::Char('l') => {
+                    self.set_cursor_x(self.cursor_x.saturating_add(repeat))
                 }
-                KeyCode::Right | KeyCode::Char('l') => self.set_cursor_x(self.cursor_x + 1),
                 KeyCode::Home | KeyCode::Char('0') => self.set_cursor_x(0),
                 KeyCode::End | KeyCode::Char('$') => self.set_cursor_x(self.cursor_line_length),
+                KeyCode::Char(' ') | KeyCode::Enter if self.selected_group.is_some() => {
+                    let group = self.selected_group.as_ref();
+                    let in_group = |task: &&mut Task| task.def.group.as_ref() == group;
+                    let collapse = !tasks.iter_mut().filter(in_group).all(|task| task.collapsed);
+                    for task in tasks.iter_mut().filter(in_group) {
+                        task.collapsed = collapse;
+                    }
+                }
                 KeyCode::Char(' ') | KeyCode::Enter => {
                     if let Some(task) = tasks.get_mut(self.selected_task_id) {
                         task.collapsed = !task.collapsed;
@@ -179,10 +768,88 @@ impl Renderer {
                         }
                     }
                 }
+                // a replay only shows what happened, there's nothing to run
+                KeyCode::Char('F' | 'R' | 'r' | 'x' | 'p') if self.read_only => {}
+                // tasks that are being shut down stay that way
+                KeyCode::Char('F' | 'R' | 'r') if self.quitting => {}
+                KeyCode::Char('p') => self.toggle_pause(tasks),
+                KeyCode::Char('F') => {
+                    for task in tasks.iter_mut().filter(|task| task.is_failed()) {
+                        if let Err(error) = task.force_restart(RestartReason::Manual) {
+                            self.show_error(&error);
+                        }
+                    }
+                }
+                KeyCode::Char('R') => {
+                    self.show_notice("restarting all tasks…".to_owned().yellow());
+                    for task in tasks.iter_mut() {
+                        if let Err(error) = task.force_restart(RestartReason::Manual) {
+                            self.show_error(&error);
+                        }
+                    }
+                }
+                KeyCode::Char('s') => self.separate_stderr = !self.separate_stderr,
+                KeyCode::Char('i') => self.interleave = !self.interleave,
+                KeyCode::Char('e') => self.hide_stderr = !self.hide_stderr,
+                KeyCode::Char('a') => {
+                    self.auto_reorder = !self.auto_reorder;
+                    self.show_notice(
+                        format!(
+                            "failed and running tasks first: {}",
+                            if self.auto_reorder { "on" } else { "off" }
+                        )
+                        .dark_grey(),
+                    );
+                }
+                KeyCode::Char('t') => {
+                    self.palette_index = (self.palette_index + 1) % Palette::ALL.len();
+                    self.show_notice(format!("colors: {}", self.palette().name()).dark_grey());
+                }
+                KeyCode::Char('m') => {
+                    if let Some(task) = tasks.get_mut(self.selected_task_id) {
+                        task.logs.push(LogLine {
+                            text: MARK.into(),
+                            stream: Stream::Stdout,
+                            kind: LogKind::Mark,
+                            level: None,
+                        });
+                    }
+                }
+                KeyCode::Char('n') if self.search.is_some() => self.jump_to_match(tasks, true),
+                KeyCode::Char('N') if self.search.is_some() => self.jump_to_match(tasks, false),
+                KeyCode::Char('n') => self.jump_to_mark(tasks, true),
+                KeyCode::Char('N') => self.jump_to_mark(tasks, false),
+                KeyCode::Char('o') => {
+                    if let Some(task) = tasks.get(self.selected_task_id) {
+                        self.show_notice(match save_logs(task) {
+                            Ok(path) => format!("saved logs to {path}").green(),
+                            Err(err) => format!("failed to save logs: {err}").red(),
+                        });
+                    }
+                }
+                KeyCode::Char('v') => {
+                    self.min_level = match self.min_level {
+                        None => Some(LogLevel::Debug),
+                        Some(LogLevel::Trace) => Some(LogLevel::Debug),
+                        Some(LogLevel::Debug) => Some(LogLevel::Info),
+                        Some(LogLevel::Info) => Some(LogLevel::Warn),
+                        Some(LogLevel::Warn) => Some(LogLevel::Error),
+                        Some(LogLevel::Error) => None,
+                    };
+                    self.show_notice(match self.min_level {
+                        Some(level) => format!("showing {level} and above").dark_grey(),
+                        None => "showing all levels".to_owned().dark_grey(),
+                    });
+                }
+                KeyCode::Char('w') => {
+                    if let Some(task) = tasks.get_mut(self.selected_task_id) {
+                        task.wrapped = !task.wrapped;
+                    }
+                }
                 KeyCode::Char('?') => self.toggle_overlay(Overlay::Help),
                 KeyCode::Char('q') => {
-                    if self.overlays.len() == 0 {
-                        self.quit(tasks);
+                    if self.overlays.is_empty() {
+                        self.request_quit(tasks, quit_confirmed);
                     } else {
                         self.overlays.pop();
                     }
@@ -194,9 +861,12 @@ impl Renderer {
                 }
                 KeyCode::Char('r') => {
                     if let Some(task) = tasks.get_mut(self.selected_task_id) {
-                        task.force_restart();
+                        if let Err(error) = task.force_restart(RestartReason::Manual) {
+                            self.show_error(&error);
+                        }
                     }
                 }
+                KeyCode::Esc if self.overlays.is_empty() => self.search = None,
                 KeyCode::Esc => {
                     self.overlays.pop();
                 }
@@ -219,63 +889,390 @@ impl Renderer {
         }
     }
 
-    fn render<'a>(&mut self, tasks: &'a [Task]) -> impl Iterator<Item = Line<'a>> + Clone {
-        let in_screen = self.in_screen;
+    /// Tasks in the order they're displayed in, which may differ from the order they were given in.
+    /// Tasks of the same group are kept together, where the first of them would be.
+    fn display_order<'a>(&self, tasks: &'a [Task]) -> Vec<&'a Task> {
+        let mut order: Vec<&Task> = tasks.iter().collect();
+        order.sort_by_key(|task| task.def.order);
+        // groups follow their first task, so a group with a failed task is moved up as a whole
+        if self.summary_sort == SummarySort::Status && !self.in_screen {
+            order.sort_by_key(|task| match task.state {
+                _ if task.timed_out => 0,
+                TaskState::Exited(TaskExitReason::Succeeded) => 1,
+                TaskState::Exited(_) => 0,
+                _ => 2,
+            });
+        }
+        if self.auto_reorder && self.in_screen {
+            order.sort_by_key(|task| match task.state {
+                TaskState::Exited(TaskExitReason::Succeeded)
+                | TaskState::Pending
+                | TaskState::Stopped => 2,
+                TaskState::Exited(_) => 0,
+                _ => 1,
+            });
+        }
 
-        tasks.iter().flat_map(move |task| {
-            std::iter::once(Line::TaskName {
+        let mut grouped: Vec<&Task> = Vec::with_capacity(order.len());
+        for task in &order {
+            if grouped.iter().any(|other| other.id == task.id) {
+                continue;
+            }
+            match &task.def.group {
+                Some(group) => grouped.extend(
+                    order
+                        .iter()
+                        .filter(|other| other.def.group.as_ref() == Some(group)),
+                ),
+                None => grouped.push(task),
+            }
+        }
+
+        // everything is printed on exit, only the interactive view is paged
+        match self.tasks_per_page {
+            Some(per_page) if self.in_screen => grouped
+                .into_iter()
+                .skip(self.task_page * per_page)
+                .take(per_page)
+                .collect(),
+            _ => grouped,
+        }
+    }
+
+    /// Shows the previous or next page of tasks, with the cursor at its top.
+    fn change_task_page(&mut self, tasks: &[Task], forward: bool) {
+        let Some(per_page) = self.tasks_per_page else {
+            return;
+        };
+        let last_page = tasks.len().saturating_sub(1) / per_page;
+        self.task_page = if forward {
+            (self.task_page + 1).min(last_page)
+        } else {
+            self.task_page.saturating_sub(1)
+        };
+        self.set_cursor_y(0);
+    }
+
+    fn task_status(&self, task: &Task) -> StyledContent<String> {
+        let status = match (&task.state, &task.def.health_url) {
+            (TaskState::Running { .. }, Some(_)) if !task.paused => self.theme.readiness(task),
+            _ => self.theme.task_state(task),
+        };
+
+        let mut text = status.content().clone();
+        if let (true, TaskState::Running { pid, .. }) = (self.stats, &task.state) {
+            text += &format!(" · pid {pid}");
+            if let Some(stats) = task.stats {
+                text += &format!(
+                    " · {:.1}% cpu · {:.1} MB",
+                    stats.cpu,
+                    stats.memory as f64 / 1_000_000.0
+                );
+            }
+        }
+        if self.counts {
+            text += &format!(
+                " · {}",
+                format_counts(task.line_total, task.stderr_lines, task.byte_total)
+            );
+        }
+        StyledContent::new(*status.style(), text)
+    }
+
+    /// Turns a log line of a task into the lines it's drawn as, several if the task's output is
+    /// wrapped, or none if its level or stream is hidden.
+    fn log_lines<'a>(&self, task: &'a Task, log: &'a LogLine) -> Vec<Line<'a>> {
+        if self.hides_stderr() && log.stream == Stream::Stderr && log.kind != LogKind::Mark {
+            return vec![];
+        }
+        // lines without a recognized level are always shown
+        if self
+            .min_level
+            .is_some_and(|min| log.level.is_some_and(|level| level < min))
+        {
+            return vec![];
+        }
+        if log.kind == LogKind::Mark {
+            return vec![Line::Mark(task.id)];
+        }
+
+        let tint = task.def.color.filter(|_| self.colorize_lines).map(dim);
+        let wrap_width = (task.wrapped && self.in_screen)
+            .then(|| self.viewport_width.saturating_sub(self.log_prefix_width()))
+            .filter(|width| *width > 0);
+        let pieces = match wrap_width {
+            Some(width) => wrap(&log.text, width),
+            None => vec![log.text.as_str()],
+        };
+        pieces
+            .into_iter()
+            .map(|text| Line::Log {
                 id: task.id,
-                name: &task.def.name,
-                color: task.def.color,
-                collapsed: task.collapsed,
+                text,
+                tint: match log.kind {
+                    LogKind::Added => Some(Color::Green),
+                    LogKind::Removed => Some(Color::DarkRed),
+                    _ => tint,
+                },
+                gutter: task.def.color.map(dim),
+                wrapped: wrap_width.is_some(),
+                kind: log.kind,
+                source: self
+                    .interleave
+                    .then_some((task.def.name.as_str(), task.def.color)),
             })
-            .chain(
-                (!task.collapsed || !in_screen)
-                    .then(|| task.logs.iter().map(|log| Line::Log(task.id, log)))
-                    .into_iter()
-                    .flatten(),
-            )
-            .chain(std::iter::once(Line::TaskStatus(
-                task.id,
-                task.state.render(),
-            )))
-        })
+            .collect()
+    }
+
+    /// Whether stderr is hidden, which only applies to the interactive view. The output printed
+    /// on exit always has all of it.
+    fn hides_stderr(&self) -> bool {
+        self.hide_stderr && self.in_screen
+    }
+
+    /// Width of what's drawn in front of a log line: the gutter, and the task's name when all
+    /// output is shown as one stream.
+    fn log_prefix_width(&self) -> usize {
+        let source_width = if self.interleave {
+            self.source_width + 1
+        } else {
+            0
+        };
+        source_width + LOG_PREFIX.width()
+    }
+
+    /// Sets the order of the log lines already there, as (task id, index in its logs), for a
+    /// replay whose lines all arrive at once.
+    pub fn set_arrival_order(&mut self, tasks: &[Task], order: Vec<(usize, usize)>) {
+        self.arrival_order = order;
+        self.ordered_logs = tasks.iter().map(|task| task.logs.len()).collect();
+    }
+
+    /// Keeps track of the order log lines arrive in, for showing all output as one stream.
+    /// Tasks only add lines at the end, besides the lines `--diff-output` inserts, so the ones
+    /// past those already seen are new.
+    fn record_arrivals(&mut self, tasks: &[Task]) {
+        self.ordered_logs.resize(tasks.len(), 0);
+        self.diff_counts.resize(tasks.len(), 0);
+        for task in tasks {
+            if self.diff_counts[task.id] != task.diff_count {
+                self.diff_counts[task.id] = task.diff_count;
+                self.remap_arrivals(task);
+            }
+            let ordered = &mut self.ordered_logs[task.id];
+            self.arrival_order
+                .extend((*ordered..task.logs.len()).map(|idx| (task.id, idx)));
+            *ordered = task.logs.len();
+        }
+        self.source_width = tasks
+            .iter()
+            .map(|task| task.def.name.width())
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// Points a task's arrivals at its lines again after `--diff-output` inserted the lines
+    /// missing since the previous run into the current one. These count as arriving right before
+    /// the line they're in front of.
+    fn remap_arrivals(&mut self, task: &Task) {
+        let inserted =
+            |idx: usize| idx >= task.run_start && task.logs[idx].kind == LogKind::Removed;
+        let mut next = 0;
+        let mut remapped = Vec::with_capacity(self.arrival_order.len());
+        for &(id, idx) in &self.arrival_order {
+            if id != task.id {
+                remapped.push((id, idx));
+                continue;
+            }
+            while next < task.logs.len() && inserted(next) {
+                remapped.push((id, next));
+                next += 1;
+            }
+            remapped.push((id, next));
+            next += 1;
+        }
+        self.arrival_order = remapped;
+        self.ordered_logs[task.id] = next;
+    }
+
+    fn render<'a>(&self, tasks: &'a [Task]) -> Vec<Line<'a>> {
+        // the final dump can be limited to the end of each task's output
+        let omitted = |task: &Task| match self.tail_on_exit {
+            Some(tail) if !self.in_screen => task.logs.len().saturating_sub(tail),
+            _ => 0,
+        };
+
+        if self.interleave {
+            let mut lines: Vec<Line> = self
+                .arrival_order
+                .iter()
+                .filter(|&&(id, idx)| idx >= omitted(&tasks[id]))
+                .flat_map(|&(id, idx)| self.log_lines(&tasks[id], &tasks[id].logs[idx]))
+                .collect();
+            // the stream doesn't say how tasks ended, so the final dump ends with that
+            if !self.in_screen {
+                if !lines.is_empty() {
+                    lines.push(Line::Empty);
+                }
+                for task in self.display_order(tasks) {
+                    lines.push(task_name_line(task));
+                    if omitted(task) > 0 {
+                        lines.push(Line::Omitted(task.id, omitted(task)));
+                    }
+                    lines.push(Line::TaskStatus(task.id, self.task_status(task)));
+                }
+            }
+            return lines;
+        }
+
+        let mut lines = Vec::new();
+
+        let mut current_group = None;
+        for task in self.display_order(tasks) {
+            if task.def.group != current_group {
+                current_group = task.def.group.clone();
+                if let Some(group) = &task.def.group {
+                    lines.push(Line::Group {
+                        id: task.id,
+                        name: group,
+                        status: group_status(tasks, group),
+                    });
+                }
+            }
+
+            lines.push(task_name_line(task));
+
+            if !task.collapsed || !self.in_screen {
+                let omitted = omitted(task);
+                if omitted > 0 {
+                    lines.push(Line::Omitted(task.id, omitted));
+                }
+                let logs_of = |stream: Option<Stream>| {
+                    task.logs[omitted..]
+                        .iter()
+                        .filter(move |log| stream.is_none_or(|stream| log.stream == stream))
+                        .flat_map(|log| self.log_lines(task, log))
+                };
+
+                if self.separate_stderr {
+                    lines.extend(logs_of(Some(Stream::Stdout)));
+                    let has_stderr = task.logs.iter().any(|log| log.stream == Stream::Stderr);
+                    if has_stderr && !self.hides_stderr() {
+                        lines.push(Line::Section(task.id, "stderr"));
+                        lines.extend(logs_of(Some(Stream::Stderr)));
+                    }
+                } else {
+                    lines.extend(logs_of(None));
+                }
+            }
+
+            lines.push(Line::TaskStatus(task.id, self.task_status(task)));
+        }
+
+        lines
+    }
+
+    /// Writes everything that isn't part of the interactive view somewhere else than stdout.
+    pub fn redirect_output(&mut self, out: Box<dyn Write>) {
+        self.stdout = out;
     }
 
     pub fn print_all_tasks(&mut self, tasks: &[Task]) -> std::io::Result<()> {
+        if !self.color && !self.raw {
+            // wraps whatever the output was redirected to, instead of going to stdout directly
+            let out = std::mem::replace(&mut self.stdout, Box::new(std::io::sink()));
+            self.stdout = Box::new(strip_ansi_escapes::Writer::new(out));
+        }
+
         self.selected_task_id = usize::MAX;
+        self.record_arrivals(tasks);
         for line in self.render(tasks) {
-            self.draw_line(line)?;
+            let mut row = vec![];
+            self.draw_line(&mut row, line)?;
+            self.stdout.write_all(&row)?;
+            self.stdout.write_all(b"\n")?;
         }
-        Ok(())
+        if let Some(elapsed) = self.elapsed() {
+            let mut total = format!("finished in {}", format_elapsed(elapsed));
+            if self.counts {
+                let sum = |count: fn(&Task) -> usize| tasks.iter().map(count).sum();
+                total += &format!(
+                    " · {}",
+                    format_counts(
+                        sum(|task| task.line_total),
+                        sum(|task| task.stderr_lines),
+                        sum(|task| task.byte_total)
+                    )
+                );
+            }
+            queue!(
+                self.stdout,
+                style::Print(total.dark_grey()),
+                style::Print("\n")
+            )?;
+        }
+        self.stdout.flush()
     }
 
-    fn draw_line(&mut self, line: Line) -> std::io::Result<usize> {
+    fn draw_line(&self, out: &mut impl Write, line: Line) -> std::io::Result<usize> {
         let len = match line {
             Line::TaskName {
                 id,
                 name,
                 color,
                 collapsed,
+                exit_reason,
+                line_rate_warning,
             } => {
-                let len = name.len();
+                let mut len = name.width();
                 let mut name = name.bold();
                 name.style_mut().foreground_color = color;
                 if collapsed && self.in_screen {
-                    self.stdout
-                        .queue(style::Print(if id == self.selected_task_id {
-                            "+ ".green()
-                        } else {
-                            "+ ".dark_grey()
-                        }))?;
-                }
-                self.stdout.queue(style::Print(name))?;
+                    out.queue(style::Print(if id == self.selected_task_id {
+                        "+ ".green()
+                    } else {
+                        "+ ".dark_grey()
+                    }))?;
+                }
+                if self.index_headers {
+                    let index = format!("[{}] ", id + 1);
+                    len += index.width();
+                    out.queue(style::Print(index.dark_grey()))?;
+                }
+                out.queue(style::Print(name))?;
+                if let Some(reason) = exit_reason {
+                    let glyph = self.theme.exit_glyph(&reason);
+                    len += 1 + glyph.content().width();
+                    queue!(out, style::Print(" "), style::Print(glyph))?;
+                }
+                // only interesting while it's still happening, not in the output printed on exit
+                if let Some(rate) = line_rate_warning.filter(|_| self.in_screen) {
+                    let warning = format!("⚠ {rate} lines/s");
+                    len += 1 + warning.width();
+                    queue!(out, style::Print(" "), style::Print(warning.yellow()))?;
+                }
+                len
+            }
+            Line::Group { name, status, .. } => {
+                let selected = self.selected_group.as_deref() == Some(name);
+                queue!(
+                    out,
+                    style::Print(if selected {
+                        "■ ".green()
+                    } else {
+                        "■ ".dark_grey()
+                    }),
+                    style::Print(name.bold().underlined()),
+                    style::Print(" "),
+                )?;
+                let len = 2 + name.width() + 1 + status.content().width();
+                queue!(out, style::Print(status))?;
                 len
             }
             Line::TaskStatus(id, status_text) => {
-                let len = STATUS_PREFIX.chars().count() + status_text.content().chars().count();
+                let len = STATUS_PREFIX.width() + status_text.content().width();
                 queue!(
-                    self.stdout,
+                    out,
                     style::Print(if id == self.selected_task_id {
                         STATUS_PREFIX.green()
                     } else {
@@ -285,35 +1282,69 @@ impl Renderer {
                 )?;
                 len
             }
-            Line::Log(id, log) => {
-                let len = log.chars().count();
-                let scrolled_log = if self.in_screen {
-                    let mut content_width = self.viewport_width - LOG_PREFIX.chars().count();
+            Line::Section(id, name) => {
+                queue!(
+                    out,
+                    style::Print(if id == self.selected_task_id {
+                        SECTION_PREFIX.green()
+                    } else {
+                        SECTION_PREFIX.dark_grey()
+                    }),
+                    style::Print(name.dark_grey().italic())
+                )?;
+                SECTION_PREFIX.width() + name.width()
+            }
+            Line::Log {
+                id,
+                text,
+                tint,
+                gutter,
+                wrapped,
+                kind,
+                source,
+            } => {
+                let tinted = |text: String| match tint {
+                    Some(tint) => text.with(tint).to_string(),
+                    None => text,
+                };
+
+                let text = if self.raw || kind == LogKind::Notice {
+                    Cow::Borrowed(text)
+                } else {
+                    sanitize(text)
+                };
+                let text = text.as_ref();
+                // escape sequences don't take up any columns
+                let len = if self.raw || kind == LogKind::Notice {
+                    strip_ansi_escapes::strip_str(text).width()
+                } else {
+                    text.width()
+                };
+                // wrapped lines already fit the viewport, so they aren't scrolled horizontally
+                let scrolled_log = if self.in_screen && !wrapped {
+                    // task names in front of the lines may take up all of the width
+                    let content_width = self.viewport_width.saturating_sub(self.log_prefix_width());
                     let clipped_left = self.scroll_x > 0;
-                    if clipped_left {
-                        content_width -= 1;
-                    }
                     let clipped_right = len > self.scroll_x + content_width;
-                    if clipped_right {
-                        content_width -= 1;
-                    }
+                    // the markers take the place of the first and last visible characters,
+                    // so everything else stays in the column the cursor expects it in
+                    let start = self.scroll_x + clipped_left as usize;
+                    let end =
+                        (self.scroll_x + content_width).saturating_sub(clipped_right as usize);
 
                     if self.scroll_x > len {
-                        &"‹".dark_grey().to_string()
+                        "‹".dark_grey().to_string()
                     } else if !clipped_left && !clipped_right {
-                        log
+                        tinted(text.to_owned())
                     } else {
-                        &format!(
+                        format!(
                             "{}{}{}",
                             if clipped_left {
                                 "‹".dark_grey().to_string()
                             } else {
                                 "".into()
                             },
-                            log.chars()
-                                .skip(self.scroll_x)
-                                .take(content_width)
-                                .collect::<String>(),
+                            tinted(clip(text, start, end.saturating_sub(start))),
                             if clipped_right {
                                 "›".dark_grey().to_string()
                             } else {
@@ -322,52 +1353,126 @@ impl Renderer {
                         )
                     }
                 } else {
-                    log
+                    tinted(text.to_owned())
+                };
+                // changes since the previous run are marked in place of the gutter
+                let prefix = match kind {
+                    LogKind::Added => "+ ".green(),
+                    LogKind::Removed => "- ".red(),
+                    _ if id == self.selected_task_id => LOG_PREFIX.green(),
+                    _ => LOG_PREFIX.with(gutter.unwrap_or(Color::DarkGrey)),
                 };
+                if let Some((name, color)) = source {
+                    // padded by columns, since wide characters take up two
+                    let padding = " ".repeat(self.source_width.saturating_sub(name.width()) + 1);
+                    let mut name = format!("{name}{padding}").bold();
+                    name.style_mut().foreground_color = color;
+                    queue!(out, style::Print(name))?;
+                }
+                queue!(out, style::Print(prefix), style::Print(scrolled_log))?;
+                self.log_prefix_width() + len
+            }
+            Line::Mark(id) => {
                 queue!(
-                    self.stdout,
+                    out,
+                    style::Print(if id == self.selected_task_id {
+                        MARK_PREFIX.green()
+                    } else {
+                        MARK_PREFIX.yellow()
+                    }),
+                    style::Print(MARK.yellow()),
+                )?;
+                MARK_PREFIX.width() + MARK.width()
+            }
+            Line::Omitted(id, count) => {
+                let text = format!("(… {count} earlier lines omitted)");
+                let len = LOG_PREFIX.width() + text.width();
+                queue!(
+                    out,
                     style::Print(if id == self.selected_task_id {
                         LOG_PREFIX.green()
                     } else {
                         LOG_PREFIX.dark_grey()
                     }),
-                    style::Print(scrolled_log),
+                    style::Print(text.dark_grey().italic()),
                 )?;
-                LOG_PREFIX.chars().count() + len
+                len
             }
             Line::Empty => 0,
         };
-        queue!(self.stdout, style::Print("\n"), cursor::MoveToColumn(0))?;
         Ok(len)
     }
 
     fn render_overlays(&mut self) -> std::io::Result<()> {
-        for overlay in &self.overlays {
+        if let Some(overlay) = self.overlays.first() {
             match overlay {
-                Overlay::Help => return help_overlay::render_help_overlay(&mut self.stdout),
+                Overlay::Help => {
+                    let (width, height) = self.size()?;
+                    help_overlay::render_help_overlay(&mut self.stdout, width, height)?
+                }
             }
         }
         Ok(())
     }
 
+    /// Draws the task counts at the start of the footer, returning their width.
+    fn draw_task_summary(&self, out: &mut impl Write, tasks: &[Task]) -> std::io::Result<usize> {
+        let (mut pending, mut running, mut done, mut failed) = (0, 0, 0, 0);
+        for task in tasks {
+            match task.state {
+                TaskState::Pending => pending += 1,
+                TaskState::Running { .. }
+                | TaskState::Stopping
+                | TaskState::Restarting { .. }
+                | TaskState::ForceRestarting(_) => running += 1,
+                TaskState::Exited(TaskExitReason::Succeeded) | TaskState::Stopped => done += 1,
+                TaskState::Exited(_) => failed += 1,
+            }
+        }
+
+        let segments = [
+            (pending, format!("{pending} pending").dark_grey()),
+            (running, format!("{running} running").yellow()),
+            (done, format!("{done} done").green()),
+            (failed, format!("{failed} failed").red()),
+        ];
+        let mut width = 1;
+        for (i, (_, segment)) in segments
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .enumerate()
+        {
+            if i > 0 {
+                width += 3;
+                queue!(out, style::Print(" · ".dark_grey()))?;
+            }
+            width += segment.content().width();
+            queue!(out, style::Print(segment))?;
+        }
+
+        queue!(out, style::Print(" "))?;
+        Ok(width)
+    }
+
     pub fn draw_tasks(&mut self, tasks: &[Task]) -> std::io::Result<()> {
-        queue!(
-            self.stdout,
-            terminal::BeginSynchronizedUpdate,
-            cursor::MoveTo(0, 0),
-            terminal::Clear(ClearType::All)
-        )?;
+        self.record_arrivals(tasks);
+        if !self.in_screen {
+            return Ok(());
+        }
 
-        let (width, height) = terminal::size()?;
+        let (width, height) = self.size()?;
         self.viewport_width = width as usize;
         self.viewport_height = height as usize;
 
         let lines = self.render(tasks);
 
-        let snap_to_bottom = self.cursor_y == self.line_count;
-        self.line_count = lines.clone().count();
+        // keep following new output while the cursor is close to the bottom,
+        // staying the same distance away from it
+        let distance_to_bottom = self.line_count.saturating_sub(self.cursor_y);
+        let snap_to_bottom = distance_to_bottom <= self.follow_threshold;
+        self.line_count = lines.len();
         if snap_to_bottom {
-            self.set_cursor_y(self.line_count);
+            self.set_cursor_y(self.line_count.saturating_sub(distance_to_bottom));
         }
 
         if self.cursor_x > self.cursor_line_length {
@@ -375,44 +1480,67 @@ impl Renderer {
             self.set_cursor_x(self.cursor_line_length);
         }
 
+        // the empty line below all tasks belongs to the last one
+        let last_task_id = lines.last().and_then(Line::task_id).unwrap_or(0);
+
         let visible_lines = lines
+            .into_iter()
             .chain(std::iter::repeat(Line::Empty))
             .skip(self.scroll_y)
-            .take(self.viewport_height - 1)
+            .take(self.viewport_height.saturating_sub(1))
             .enumerate();
 
-        self.selected_task_id = visible_lines
+        let selected_line = visible_lines
             .clone()
             .find(|(idx, _)| self.cursor_y - self.scroll_y == *idx)
-            .and_then(|(_, line)| line.task_id())
-            .unwrap_or(tasks.len() - 1);
+            .map(|(_, line)| line);
+        self.selected_task_id = selected_line
+            .as_ref()
+            .and_then(Line::task_id)
+            .unwrap_or(last_task_id);
+        self.selected_group = match selected_line {
+            Some(Line::Group { name, .. }) => Some(name.to_owned()),
+            _ => None,
+        };
 
+        let mut rows = vec![];
         for (idx, line) in visible_lines {
-            let length = self.draw_line(line)?;
+            let mut row = vec![];
+            let length = self.draw_line(&mut row, line)?;
+            rows.push(self.palette().recolor(row));
             if self.cursor_y - self.scroll_y == idx {
                 self.cursor_line_length = length;
             } else if self.cursor_y == self.viewport_height {
                 self.cursor_line_length = 0;
             }
         }
+        rows.push(self.palette().recolor(self.draw_footer(tasks)?));
 
-        queue!(
-            self.stdout,
-            style::Print(format!("{} tasks ", tasks.len()).green())
-        )?;
-
-        help_overlay::print_key(&mut self.stdout, "q", "quit")?;
-        help_overlay::print_key(&mut self.stdout, "←↓↑→/hjkl", "navigate")?;
-        help_overlay::print_key(&mut self.stdout, "?", "help")?;
-
-        let version = concat!("congregation ", env!("CARGO_PKG_VERSION"));
-        queue!(
-            self.stdout,
-            cursor::MoveToColumn((self.viewport_width - version.len()) as u16),
-            style::Print(version.dark_grey()),
-        )?;
+        // a resize rearranges everything, so the whole screen is redrawn instead of the changed rows
+        let full_redraw = self.frame.len() != rows.len() || self.frame_width != self.viewport_width;
+        queue!(self.stdout, terminal::BeginSynchronizedUpdate)?;
+        if full_redraw {
+            self.clear_rows()?;
+        }
+        for (idx, row) in rows.iter().enumerate() {
+            if !full_redraw && self.frame[idx] == *row {
+                continue;
+            }
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, idx as u16),
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            self.stdout.write_all(row)?;
+        }
+        self.frame = rows;
+        self.frame_width = self.viewport_width;
 
-        self.render_overlays()?;
+        // overlays are drawn over the rows, which have to be redrawn in full once they're closed
+        if !self.overlays.is_empty() {
+            self.render_overlays()?;
+            self.frame.clear();
+        }
 
         queue!(
             self.stdout,
@@ -425,4 +1553,502 @@ impl Renderer {
 
         self.stdout.flush()
     }
+
+    /// Draws the footer below the tasks: the label, task counts, key hints, session time and version.
+    fn draw_footer(&self, tasks: &[Task]) -> std::io::Result<Vec<u8>> {
+        let mut out = vec![];
+        let mut footer_width = 0;
+        if let Some(label) = &self.label {
+            footer_width += label.width() + 1;
+            queue!(out, style::Print(label.as_str().bold()), style::Print(" "))?;
+        }
+        if let Some(per_page) = self
+            .tasks_per_page
+            .filter(|per_page| tasks.len() > *per_page)
+        {
+            let first = self.task_page * per_page + 1;
+            let last = (first + per_page - 1).min(tasks.len());
+            let page = match last - first {
+                0 => format!("task {first} of {}", tasks.len()),
+                _ => format!("tasks {first}–{last} of {}", tasks.len()),
+            };
+            footer_width += page.width() + 3;
+            queue!(
+                out,
+                style::Print(page.bold()),
+                style::Print(" · ".dark_grey())
+            )?;
+        }
+        if self.hide_stderr {
+            let hidden = "stderr hidden";
+            footer_width += hidden.width() + 3;
+            queue!(
+                out,
+                style::Print(hidden.yellow()),
+                style::Print(" · ".dark_grey())
+            )?;
+        }
+        let summary_width = footer_width + self.draw_task_summary(&mut out, tasks)?;
+        let version = concat!("congregation ", env!("CARGO_PKG_VERSION"));
+        let right = match self.elapsed() {
+            Some(elapsed) => format!("{} · {version}", format_elapsed(elapsed)),
+            None => version.to_owned(),
+        };
+        let right_width = right.width();
+
+        // a notice takes the place of the key hints, clipped to fit in front of the version
+        if let Some(notice) = &self.notice {
+            let max_width = self
+                .viewport_width
+                .saturating_sub(summary_width + right_width + 1);
+            let text = notice.content();
+            let text = if text.width() > max_width {
+                let mut clipped = clip(text, 0, max_width.saturating_sub(1));
+                clipped.push('…');
+                clipped
+            } else {
+                text.clone()
+            };
+            queue!(out, style::Print(StyledContent::new(*notice.style(), text)))?;
+        } else if let Some(input) = &self.search_input {
+            queue!(out, style::Print(format!("/{input}")))?;
+        } else if self.key_hints {
+            let max_width = self
+                .viewport_width
+                .saturating_sub(summary_width + right_width + 1);
+            self.draw_key_hints(&mut out, tasks, max_width)?;
+        }
+
+        queue!(
+            out,
+            cursor::MoveToColumn(self.viewport_width.saturating_sub(right_width) as u16),
+            style::Print(right.dark_grey()),
+        )?;
+        Ok(out)
+    }
+
+    /// Draws the hints for the most useful keys, leaving out the ones that don't fit in
+    /// `max_width` on narrow terminals.
+    fn draw_key_hints(
+        &self,
+        out: &mut impl Write,
+        tasks: &[Task],
+        max_width: usize,
+    ) -> std::io::Result<()> {
+        let mut hints = vec![];
+        // tasks waiting for their start delay start on their own
+        if tasks
+            .iter()
+            .any(|task| matches!(task.state, TaskState::Pending) && task.start_at.is_none())
+        {
+            hints.push(("space", "start tasks"));
+        }
+        if self.search.is_some() {
+            hints.push(("n/N", "next/previous match"));
+        }
+        hints.extend([("q", "quit"), ("←↓↑→/hjkl", "navigate"), ("?", "help")]);
+
+        let mut width = 0;
+        for (key, name) in hints {
+            // the key in a box, followed by its name and a space
+            width += key.width() + name.width() + 4;
+            if width > max_width {
+                break;
+            }
+            help_overlay::print_key(out, key, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the searches of previous sessions. A missing or unreadable file just means there's no
+/// history yet.
+fn read_search_history(path: &PathBuf) -> Vec<String> {
+    let history = std::fs::read_to_string(path).unwrap_or_default();
+    let searches: Vec<String> = history
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect();
+    let skip = searches.len().saturating_sub(MAX_SEARCH_HISTORY);
+    searches.into_iter().skip(skip).collect()
+}
+
+/// Describes how much a task printed, like `1.2k lines (40 on stderr), 340 KB`.
+fn format_counts(lines: usize, stderr_lines: usize, bytes: usize) -> String {
+    let short = |count: usize| match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", count as f64 / 1_000.0),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    };
+    let size = match bytes {
+        0..1_000 => format!("{bytes} B"),
+        1_000..1_000_000 => format!("{:.0} KB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+    };
+    let unit = if lines == 1 { "line" } else { "lines" };
+    match stderr_lines {
+        0 => format!("{} {unit}, {size}", short(lines)),
+        _ => format!(
+            "{} {unit} ({} on stderr), {size}",
+            short(lines),
+            short(stderr_lines)
+        ),
+    }
+}
+
+/// Formats a duration for humans, like `42s`, `3m 07s` or `1h 02m 03s`.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m {secs:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {secs:02}s")
+    } else {
+        format!("{secs}s")
+    }
+}
+
+/// Writes a task's logs to a new file in the current directory, named after the task and the
+/// current time, returning the file name.
+fn save_logs(task: &Task) -> std::io::Result<String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let name: String = task
+        .def
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let path = format!("{}-{timestamp}.log", name.trim_matches('-'));
+
+    let mut file = std::fs::File::create_new(&path)?;
+    for log in task.logs.iter().filter(|log| log.kind != LogKind::Removed) {
+        writeln!(file, "{}", strip_ansi_escapes::strip_str(&log.text))?;
+    }
+    Ok(path)
+}
+
+/// The header line of a task, with how it exited if it did.
+fn task_name_line(task: &Task) -> Line<'_> {
+    Line::TaskName {
+        id: task.id,
+        name: &task.def.name,
+        color: task.def.color,
+        collapsed: task.collapsed,
+        exit_reason: match task.state {
+            TaskState::Exited(reason) => Some(reason),
+            _ => None,
+        },
+        line_rate_warning: task.line_rate_warning,
+    }
+}
+
+/// The combined status of a group's tasks, with failures taking precedence over running tasks.
+fn group_status(tasks: &[Task], group: &str) -> StyledContent<String> {
+    let members = || {
+        tasks
+            .iter()
+            .filter(|task| task.def.group.as_deref() == Some(group))
+    };
+
+    if members().any(Task::is_failed) {
+        "some failed".to_owned().red()
+    } else if members().all(|task| matches!(task.state, TaskState::Pending)) {
+        "pending".to_owned().dark_grey()
+    } else if members().all(Task::is_finished) {
+        "all done".to_owned().green()
+    } else {
+        "running".to_owned().yellow()
+    }
+}
+
+/// Replaces control characters with their visible Unicode symbols (e.g. `␇` for a bell),
+/// so they can't beep or move the cursor and break the layout. Tabs are kept as they are.
+fn sanitize(text: &str) -> Cow<'_, str> {
+    let is_unprintable = |c: char| c.is_control() && c != '\t';
+    if !text.contains(is_unprintable) {
+        return Cow::Borrowed(text);
+    }
+
+    text.chars()
+        .map(|c| match c {
+            '\0'..='\x1f' if c != '\t' => char::from_u32(0x2400 + c as u32).unwrap(),
+            '\x7f' => '␡',
+            c if c.is_control() => '�',
+            c => c,
+        })
+        .collect()
+}
+
+/// Takes `count` columns of a line, starting at column `start`. Escape sequences are never cut in
+/// half and don't take up columns, and the colors set before `start` are kept, so scrolling
+/// through colored output doesn't garble or lose its styling. Wide characters that are cut by
+/// either edge are replaced with spaces, so the result is exactly as wide as asked for.
+fn clip(text: &str, start: usize, count: usize) -> String {
+    let mut clipped = String::new();
+    let mut has_escapes = false;
+    let mut column = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c == '\x1b' {
+            let end = escape_sequence_end(text, index);
+            let sequence = &text[index..end];
+            // styles before the visible part still apply to it, other sequences are dropped
+            if column >= start || sequence.ends_with('m') {
+                clipped.push_str(sequence);
+                has_escapes = true;
+            }
+            while chars.next_if(|&(next, _)| next < end).is_some() {}
+            continue;
+        }
+
+        let width = c.width().unwrap_or(0);
+        let end = start + count;
+        if column >= end {
+            break;
+        }
+        if column >= start && column + width <= end {
+            clipped.push(c);
+        } else if column + width > start {
+            // only part of a wide character is visible
+            let visible = (column + width).min(end) - column.max(start);
+            clipped.push_str(&" ".repeat(visible));
+        }
+        column += width;
+    }
+    // the styles must not leak into the scroll markers after the line
+    if has_escapes {
+        clipped.push_str("\x1b[0m");
+    }
+    clipped
+}
+
+/// Finds the end of the escape sequence starting at `start`, which is right after its final
+/// character. Sequences cut off at the end of the text end with it.
+fn escape_sequence_end(text: &str, start: usize) -> usize {
+    let bytes = text.as_bytes();
+    match bytes.get(start + 1) {
+        // CSI, e.g. colors: parameters up to a final byte in `@`..=`~`
+        Some(b'[') => bytes[start + 2..]
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map_or(text.len(), |end| start + 2 + end + 1),
+        // OSC, e.g. hyperlinks: ended by a bell or `ESC \`
+        Some(b']') => {
+            let rest = &text[start + 2..];
+            match (rest.find('\x07'), rest.find("\x1b\\")) {
+                (Some(bell), Some(st)) if st < bell => start + 2 + st + 2,
+                (Some(bell), _) => start + 2 + bell + 1,
+                (None, Some(st)) => start + 2 + st + 2,
+                (None, None) => text.len(),
+            }
+        }
+        Some(_) => start + 1 + text[start + 1..].chars().next().unwrap().len_utf8(),
+        None => text.len(),
+    }
+}
+
+/// Splits a line into pieces of at most `width` columns. A wide character that doesn't fit at the
+/// end of a piece starts the next one.
+fn wrap(text: &str, width: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut piece_start = 0;
+    let mut piece_width = 0;
+    for (index, c) in text.char_indices() {
+        let char_width = c.width().unwrap_or(0);
+        if piece_width + char_width > width && piece_width > 0 {
+            pieces.push(&text[piece_start..index]);
+            piece_start = index;
+            piece_width = 0;
+        }
+        piece_width += char_width;
+    }
+    pieces.push(&text[piece_start..]);
+    pieces
+}
+
+/// A dimmer shade of a task color, blended halfway towards grey so tinted output stays readable.
+fn dim(color: Color) -> Color {
+    match color {
+        Color::Rgb { r, g, b } => {
+            let blend = |channel: u8| ((channel as u16 + 128) / 2) as u8;
+            Color::Rgb {
+                r: blend(r),
+                g: blend(g),
+                b: blend(b),
+            }
+        }
+        color => color,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arg_parser::{parse_task, NameFrom};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Output that can still be read after it's been handed to the renderer.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        /// Everything written so far, without escape sequences.
+        fn text(&self) -> String {
+            strip_ansi_escapes::strip_str(String::from_utf8_lossy(&self.0.borrow()))
+        }
+    }
+
+    impl Renderer {
+        /// Draws a frame of the given size without switching the terminal to raw mode.
+        fn draw_frame(&mut self, tasks: &[Task], width: u16, height: u16) -> std::io::Result<()> {
+            self.size_override = (Some(width), Some(height));
+            self.in_screen = true;
+            self.draw_tasks(tasks)
+        }
+    }
+
+    fn task(args: &[&str], id: usize, lines: &[&str]) -> Task {
+        let mut args = args.iter().map(|arg| arg.to_string()).peekable();
+        let def = parse_task(&mut args, id as i32, NameFrom::Dir).unwrap();
+        let (tx, _) = tokio::sync::mpsc::channel(1);
+        let mut task = Task::new(def, id, tx);
+        for line in lines {
+            task.logs.push(LogLine {
+                text: line.to_string(),
+                stream: Stream::Stdout,
+                kind: LogKind::Output,
+                level: None,
+            });
+        }
+        task
+    }
+
+    #[test]
+    fn draws_tasks_into_output() {
+        let output = SharedOutput::default();
+        let mut renderer = Renderer::with_output(&Options::default(), Box::new(output.clone()));
+        let tasks = [
+            task(&["run", "echo hi", "-n", "web"], 0, &["hello", "world"]),
+            task(&["run", "true", "-n", "db"], 1, &[]),
+        ];
+
+        renderer.draw_frame(&tasks, 40, 10).unwrap();
+
+        let frame = output.text();
+        assert!(frame.contains("web"));
+        assert!(frame.contains("│ hello"));
+        assert!(frame.contains("│ world"));
+        assert!(frame.contains("db"));
+        assert!(frame.contains("└ pending"));
+        assert!(frame.find("web") < frame.find("db"));
+    }
+
+    #[test]
+    fn clips_lines_to_the_frame_width() {
+        let output = SharedOutput::default();
+        let mut renderer = Renderer::with_output(&Options::default(), Box::new(output.clone()));
+        let long_line = "x".repeat(100);
+        let tasks = [task(&["run", "true", "-n", "long"], 0, &[&long_line])];
+
+        renderer.draw_frame(&tasks, 20, 6).unwrap();
+
+        let frame = output.text();
+        assert!(frame.contains(&format!("│ {}›", "x".repeat(17))));
+        assert!(!frame.contains(&"x".repeat(19)));
+    }
+
+    #[test]
+    fn keeps_the_stream_in_order_after_a_diff() {
+        let output = SharedOutput::default();
+        let options = Options {
+            interleave: true,
+            ..Options::default()
+        };
+        let mut renderer = Renderer::with_output(&options, Box::new(output.clone()));
+        let mut tasks = [
+            task(&["run", "true", "-n", "a"], 0, &["one", "three"]),
+            task(&["run", "true", "-n", "b"], 1, &["four"]),
+        ];
+        tasks[0].previous_run = Some(vec!["one".into(), "two".into(), "three".into()]);
+
+        renderer.draw_frame(&tasks, 40, 8).unwrap();
+        tasks[0].diff_with_previous_run();
+        output.0.borrow_mut().clear();
+        renderer.draw_frame(&tasks, 40, 8).unwrap();
+
+        let frame = output.text();
+        let positions = ["two", "three", "four"].map(|text| frame.find(text));
+        assert!(positions.iter().all(Option::is_some));
+        assert!(positions.is_sorted());
+    }
+
+    #[test]
+    fn draws_names_wider_than_the_frame() {
+        let output = SharedOutput::default();
+        let options = Options {
+            interleave: true,
+            ..Options::default()
+        };
+        let mut renderer = Renderer::with_output(&options, Box::new(output.clone()));
+        let tasks = [task(&["run", "echo a very long command"], 0, &["hello"])];
+
+        renderer.draw_frame(&tasks, 12, 6).unwrap();
+
+        assert!(output.text().contains("#1"));
+    }
+
+    #[test]
+    fn ends_the_interleaved_output_on_exit_with_each_task_status() {
+        let output = SharedOutput::default();
+        let options = Options {
+            interleave: true,
+            tail_on_exit: Some(1),
+            ..Options::default()
+        };
+        let mut renderer = Renderer::with_output(&options, Box::new(output.clone()));
+        let mut tasks = [
+            task(&["run", "true", "-n", "web"], 0, &["one", "two"]),
+            task(&["run", "false", "-n", "db"], 1, &["three"]),
+        ];
+        tasks[0].state = TaskState::Exited(TaskExitReason::Succeeded);
+        tasks[1].state = TaskState::Exited(TaskExitReason::Failed(2));
+
+        renderer.print_all_tasks(&tasks).unwrap();
+
+        let printed = output.text();
+        assert!(!printed.contains("one"));
+        assert!(printed.contains("two"));
+        assert!(printed.contains("1 earlier lines omitted"));
+        assert!(printed.contains("failed (code 2)"));
+        assert!(printed.find("three") < printed.find("failed (code 2)"));
+    }
+
+    #[test]
+    fn focuses_a_task_without_a_view() {
+        let mut renderer = Renderer::with_output(&Options::default(), Box::new(std::io::sink()));
+        let tasks = [
+            task(&["run", "true", "-n", "a"], 0, &["one"]),
+            task(&["run", "false", "-n", "b"], 1, &["two"]),
+        ];
+
+        renderer.focus_task(&tasks, 1);
+        renderer.scroll_to_top(0);
+    }
 }
@@ -0,0 +1,135 @@
+use crate::arg_parser::{parse_task, NameFrom};
+use crate::diagnostics::Error;
+use crate::task::{LogKind, LogLine, Stream, Task, TaskExitReason, TaskMessage, TaskState};
+use crossterm::style::Stylize;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tokio::sync::mpsc::Sender;
+
+/// A line written by `--json`: either a line of output or the end of a run.
+#[derive(Debug, Deserialize)]
+struct Event {
+    /// Missing in recordings from before ids were written, which tell tasks apart by name.
+    id: Option<usize>,
+    task: String,
+    color: Option<String>,
+    stream: Option<String>,
+    text: Option<String>,
+    status: Option<String>,
+    code: Option<serde_json::Value>,
+    /// When the event was recorded, in milliseconds since the Unix epoch. Missing in recordings
+    /// from before times were written.
+    time: Option<u128>,
+}
+
+/// A session recorded with `--json`, as read by [`read_replay`].
+pub struct Replay {
+    pub tasks: Vec<Task>,
+    /// Every log line as (task id, index in its logs), in the order they were recorded in.
+    pub arrival_order: Vec<(usize, usize)>,
+}
+
+/// Rebuilds the tasks of a session recorded with `--json`, with their output and how they ended,
+/// without running anything.
+pub fn read_replay(path: &Path, tx: &Sender<TaskMessage>) -> Result<Replay, Error> {
+    let error = |message| Error {
+        title: "failed to replay session".into(),
+        message: format!("{}: {message}", path.display()),
+        ..Error::default()
+    };
+
+    let file = std::fs::File::open(path).map_err(|err| error(err.to_string()))?;
+    let mut tasks: Vec<Task> = Vec::new();
+    // the recorded id and name of each task, which tasks with the same name differ in
+    let mut keys: Vec<(Option<usize>, String)> = Vec::new();
+    let mut arrivals = Vec::new();
+    // events without a time keep their place in the file
+    let mut time = 0;
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|err| error(err.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: Event = serde_json::from_str(&line)
+            .map_err(|err| error(format!("invalid event on line {}: {err}", number + 1)))?;
+
+        time = event.time.unwrap_or(time);
+        let key = (event.id, event.task);
+        let id = match keys.iter().position(|other| *other == key) {
+            Some(id) => id,
+            None => {
+                let mut args = ["run", "-n", &key.1, ""].map(str::to_owned).to_vec();
+                if let Some(color) = event.color {
+                    args.extend(["-c".to_owned(), color]);
+                }
+                let mut args = args.into_iter().peekable();
+                let def = parse_task(&mut args, tasks.len() as i32, NameFrom::Dir)?;
+                let mut task = Task::new(def, tasks.len(), tx.clone());
+                // tasks that didn't end before the recording did are shown as stopped
+                task.state = TaskState::Stopped;
+                tasks.push(task);
+                keys.push(key);
+                tasks.len() - 1
+            }
+        };
+        let task = &mut tasks[id];
+
+        if let Some(status) = event.status {
+            task.state = match status.as_str() {
+                "stopped" => TaskState::Stopped,
+                _ => TaskState::Exited(exit_reason(&status, event.code).ok_or_else(|| {
+                    error(format!("unknown status '{status}' on line {}", number + 1))
+                })?),
+            };
+        } else if let Some(text) = event.text {
+            // output after a run ended belongs to a restart
+            if let TaskState::Exited(_) = task.state {
+                task.state = TaskState::Stopped;
+                arrivals.push((time, id, task.logs.len()));
+                task.logs.push(LogLine {
+                    text: "task restarted".dark_grey().italic().to_string(),
+                    stream: Stream::Stdout,
+                    kind: LogKind::Notice,
+                    level: None,
+                });
+            }
+            arrivals.push((time, id, task.logs.len()));
+            task.logs.push(LogLine {
+                text,
+                stream: match event.stream.as_deref() {
+                    Some("stderr") => Stream::Stderr,
+                    _ => Stream::Stdout,
+                },
+                kind: LogKind::Output,
+                level: None,
+            });
+        }
+    }
+    // the sort is stable, so lines recorded at the same time stay in the order of the file
+    arrivals.sort_by_key(|&(time, _, _)| time);
+    Ok(Replay {
+        tasks,
+        arrival_order: arrivals.into_iter().map(|(_, id, idx)| (id, idx)).collect(),
+    })
+}
+
+fn exit_reason(status: &str, code: Option<serde_json::Value>) -> Option<TaskExitReason> {
+    match status {
+        "succeeded" => Some(TaskExitReason::Succeeded),
+        "failed" => Some(TaskExitReason::Failed(code?.as_i64()? as i32)),
+        "killed" => Some(TaskExitReason::Killed(signal_name(code?.as_str()?))),
+        _ => None,
+    }
+}
+
+/// The static name of a recorded signal, the same one a task killed by it gets. Signals without
+/// a name, like real-time signals, and any signal on Windows, are replayed as unknown.
+#[allow(unused_variables)]
+fn signal_name(name: &str) -> &'static str {
+    #[cfg(unix)]
+    if let Ok(signal) = name.parse::<nix::sys::signal::Signal>() {
+        return signal.as_str();
+    }
+    "unknown signal"
+}
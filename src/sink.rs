@@ -0,0 +1,243 @@
+use crate::arg_parser::Options;
+use crate::diagnostics::Error;
+use crate::renderer::format_elapsed;
+use crate::task::{LogKind, LogLine, Stream, Task, TaskExitReason, TaskState};
+use crate::theme::Theme;
+use crossterm::style::Color;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A destination for task output besides the interactive view, which reads the task's logs
+/// directly. Every line and status change is passed to all sinks of the session.
+pub trait Sink {
+    /// Receives a line of output right after it was added to the task's logs.
+    fn line(&mut self, task: &Task, log: &LogLine);
+
+    /// Receives a task whose run just ended, in its new state.
+    fn status(&mut self, task: &Task);
+
+    /// Called once the session is over, with how long it took if any task was started.
+    fn finish(&mut self, _elapsed: Option<Duration>) {}
+}
+
+/// Opens the sinks that write to files, which are given on the command line.
+pub fn open_file_sinks(options: &Options) -> Result<Vec<Box<dyn Sink>>, Error> {
+    let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+    if let Some(path) = &options.log_file {
+        sinks.push(Box::new(LogFileSink {
+            file: create(path)?,
+            annotate_exit: options.annotate_exit,
+        }));
+    }
+    if let Some(path) = &options.json {
+        sinks.push(Box::new(JsonSink {
+            file: create(path)?,
+        }));
+    }
+    Ok(sinks)
+}
+
+fn create(path: &Path) -> Result<LineWriter<File>, Error> {
+    File::create(path)
+        .map(LineWriter::new)
+        .map_err(|err| Error {
+            title: "failed to create output file".into(),
+            message: format!("{}: {err}", path.display()),
+            ..Error::default()
+        })
+}
+
+/// How long the run that just ended took, for `--annotate-exit`, like ` after 12s`.
+pub fn run_duration(task: &Task) -> String {
+    task.started_at
+        .map(|started_at| format!(" after {}", format_elapsed(started_at.elapsed())))
+        .unwrap_or_default()
+}
+
+/// Whether a line is output of the task, rather than something added by congregation.
+fn is_output(log: &LogLine) -> bool {
+    matches!(log.kind, LogKind::Output | LogKind::Added)
+}
+
+/// A line of output or the end of a run, as written by the file sinks. Every record carries
+/// the task it belongs to, so tools reading the files can tell tasks and streams apart.
+#[derive(Serialize)]
+struct LogRecord<'a> {
+    /// The task's number, counting from 0, which stays unique when names don't.
+    id: usize,
+    task: &'a str,
+    /// The task's color as `rrggbb`, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    /// When the record was written, in milliseconds since the Unix epoch.
+    time: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'static str>,
+    /// The exit code or signal of a run, or null if it was stopped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<Value>,
+}
+
+impl<'a> LogRecord<'a> {
+    fn new(task: &'a Task) -> Self {
+        Self {
+            id: task.id,
+            task: &task.def.name,
+            color: task.def.color.and_then(|color| match color {
+                Color::Rgb { r, g, b } => Some(format!("{r:02x}{g:02x}{b:02x}")),
+                _ => None,
+            }),
+            time: now_millis(),
+            stream: None,
+            text: None,
+            status: None,
+            code: None,
+        }
+    }
+
+    /// A record of a line of output, or none if the line was added by congregation.
+    fn line(task: &'a Task, log: &LogLine) -> Option<Self> {
+        is_output(log).then(|| Self {
+            stream: Some(match log.stream {
+                Stream::Stdout => "stdout",
+                Stream::Stderr => "stderr",
+            }),
+            text: Some(log.text.clone()),
+            ..Self::new(task)
+        })
+    }
+
+    /// A record of how a task's run ended, or none if it's still running.
+    fn status(task: &'a Task) -> Option<Self> {
+        let (status, code) = match &task.state {
+            TaskState::Exited(reason)
+            | TaskState::Restarting {
+                exit_reason: reason,
+                ..
+            } => match reason {
+                TaskExitReason::Succeeded => ("succeeded", json!(0)),
+                TaskExitReason::Failed(code) => ("failed", json!(code)),
+                TaskExitReason::Killed(signal) => ("killed", json!(signal.to_string())),
+            },
+            TaskState::Stopped => ("stopped", Value::Null),
+            _ => return None,
+        };
+        Some(Self {
+            status: Some(status),
+            code: Some(code),
+            ..Self::new(task)
+        })
+    }
+}
+
+/// Writes every line of output to a file as plain text, prefixed with the time, the task's id,
+/// the stream and the task's name.
+struct LogFileSink {
+    file: LineWriter<File>,
+    /// Whether status lines say how long the run took.
+    annotate_exit: bool,
+}
+
+impl Sink for LogFileSink {
+    fn line(&mut self, task: &Task, log: &LogLine) {
+        if let Some(record) = LogRecord::line(task, log) {
+            let text = strip_ansi_escapes::strip_str(record.text.as_deref().unwrap_or_default());
+            self.write(&record, &text);
+        }
+    }
+
+    fn status(&mut self, task: &Task) {
+        let Some(record) = LogRecord::status(task) else {
+            return;
+        };
+        let status = Theme::default().state(&task.state);
+        let duration = if self.annotate_exit {
+            run_duration(task)
+        } else {
+            String::new()
+        };
+        self.write(&record, &format!("{}{duration}", status.content()));
+    }
+
+    /// Writes how long the session took with the same prefix as every other line, as a status
+    /// of `congregation` itself, which has `-` for an id.
+    fn finish(&mut self, elapsed: Option<Duration>) {
+        if let Some(elapsed) = elapsed {
+            let text = format!("finished in {}", format_elapsed(elapsed));
+            self.write_line(now_millis(), "-", "status", "congregation", &text);
+        }
+    }
+}
+
+impl LogFileSink {
+    /// Writes a line like `08:15:02.347 0 stdout [web] listening on :3000`, where status
+    /// changes take the place of the stream with `status`.
+    fn write(&mut self, record: &LogRecord, text: &str) {
+        let id = record.id.to_string();
+        let stream = record.stream.unwrap_or("status");
+        self.write_line(record.time, &id, stream, record.task, text);
+    }
+
+    fn write_line(&mut self, time: u128, id: &str, stream: &str, task: &str, text: &str) {
+        let _ = writeln!(
+            self.file,
+            "{} {id} {stream} [{task}] {text}",
+            clock_time(time)
+        );
+    }
+}
+
+/// The current time in milliseconds since the Unix epoch.
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// The UTC time of day of a timestamp in milliseconds since the Unix epoch, like `08:15:02.347`.
+fn clock_time(millis: u128) -> String {
+    let secs = millis / 1000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        millis % 1000
+    )
+}
+
+/// Writes every line of output and status change to a file as a JSON object per line.
+struct JsonSink {
+    file: LineWriter<File>,
+}
+
+impl JsonSink {
+    fn write(&mut self, record: &LogRecord) {
+        if let Ok(json) = serde_json::to_string(record) {
+            let _ = writeln!(self.file, "{json}");
+        }
+    }
+}
+
+impl Sink for JsonSink {
+    fn line(&mut self, task: &Task, log: &LogLine) {
+        if let Some(record) = LogRecord::line(task, log) {
+            self.write(&record);
+        }
+    }
+
+    fn status(&mut self, task: &Task) {
+        if let Some(record) = LogRecord::status(task) {
+            self.write(&record);
+        }
+    }
+}
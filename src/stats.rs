@@ -0,0 +1,63 @@
+use crate::task::{Task, TaskState};
+use std::collections::HashMap;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// CPU and memory usage of a task, including every process it started.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessStats {
+    /// In percent of a single core.
+    pub cpu: f32,
+    /// Resident memory in bytes.
+    pub memory: u64,
+}
+
+pub struct StatsCollector {
+    system: System,
+}
+
+impl StatsCollector {
+    pub fn new() -> Self {
+        Self {
+            system: System::new(),
+        }
+    }
+
+    /// Refreshes the usage of all processes and sums it up for the process tree of each running
+    /// task. CPU usage is measured between two refreshes, so it's only accurate from the second.
+    pub fn update(&mut self, tasks: &mut [Task]) {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_cpu()
+                .with_memory()
+                .without_tasks(),
+        );
+
+        let processes = self.system.processes();
+        let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+        for (pid, process) in processes {
+            if let Some(parent) = process.parent() {
+                children.entry(parent).or_default().push(*pid);
+            }
+        }
+
+        for task in tasks {
+            let TaskState::Running { pid, .. } = task.state else {
+                task.stats = None;
+                continue;
+            };
+
+            let mut stats = ProcessStats::default();
+            let mut pending = vec![Pid::from_u32(pid)];
+            while let Some(pid) = pending.pop() {
+                if let Some(process) = processes.get(&pid) {
+                    stats.cpu += process.cpu_usage();
+                    stats.memory += process.memory();
+                }
+                pending.extend(children.get(&pid).into_iter().flatten());
+            }
+            task.stats = Some(stats);
+        }
+    }
+}
@@ -1,18 +1,53 @@
-use crossterm::style::{Color, StyledContent, Stylize};
+use crate::diagnostics::Error;
+use crate::health::{spawn_health_check, HealthUrl};
+use crate::stats::ProcessStats;
+use crossterm::style::{Color, Stylize};
+use encoding_rs::Encoding;
+use globset::GlobSet;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::Regex;
+use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{ChildStdin, Command};
-use tokio::sync::mpsc::Sender;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc::{self, Sender};
 use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// The longest delay exponential backoff grows to between restarts.
+const MAX_RESTART_DELAY_SECS: u32 = 300;
+
+/// How far back failures are counted for `--crashloop-threshold`.
+const CRASHLOOP_WINDOW: Duration = Duration::from_secs(60);
+
+/// Runs with more output lines than this aren't compared with `--diff-output`.
+const MAX_DIFF_LINES: usize = 10_000;
+
+/// How long file changes have to settle down before a watching task is restarted.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
 
 #[derive(Clone, Debug)]
 pub enum TaskMessageKind {
-    Stdout(String),
+    Output(Stream, String),
     Exited(TaskExitReason),
     Restarting(u32),
     Restart,
+    /// The task's start delay is over.
+    Start,
+    FileChanged,
+    /// The task's health URL responded successfully.
+    Ready,
+    /// The task's health URL didn't respond successfully within its `--ready-timeout`.
+    NotReady,
 }
 
 #[derive(Clone, Debug)]
@@ -28,12 +63,20 @@ pub enum TaskExitReason {
     Killed(&'static str),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestartReason {
+    Manual,
+    FileChanged,
+}
+
 #[derive(Debug)]
 pub enum TaskState {
+    Pending,
     Running {
         pid: u32,
         stdin: Option<ChildStdin>,
     },
+    Stopping,
     Stopped,
     Exited(TaskExitReason),
     Restarting {
@@ -41,39 +84,97 @@ pub enum TaskState {
         remaining_secs: u32,
         cancel_tx: oneshot::Sender<()>,
     },
-    ForceRestarting,
+    ForceRestarting(RestartReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryBackoff {
+    /// Wait the same delay before every restart.
+    Fixed,
+    /// Double the delay after each restart that follows a failure.
+    Exponential,
 }
 
-impl TaskExitReason {
-    pub fn render(&self) -> StyledContent<String> {
-        match self {
-            TaskExitReason::Succeeded => "completed".to_owned().green(),
-            TaskExitReason::Killed(signal) => format!("killed ({signal})").red(),
-            TaskExitReason::Failed(code) => format!("failed (code {code})").red(),
+impl FromStr for RetryBackoff {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Self::Fixed),
+            "exponential" => Ok(Self::Exponential),
+            _ => Err(()),
         }
     }
 }
 
-impl TaskState {
-    pub fn render(&self) -> StyledContent<String> {
-        match self {
-            TaskState::Running { .. } => "running...".to_owned().green(),
-            TaskState::Stopped => "stopped".to_owned().green(),
-            TaskState::Exited(reason) => reason.render(),
-            TaskState::Restarting {
-                exit_reason,
-                remaining_secs,
-                ..
-            } => format!(
-                "{}: restarting in {remaining_secs}s...",
-                exit_reason.render().content()
-            )
-            .yellow(),
-            TaskState::ForceRestarting => "restarting...".to_owned().yellow(),
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogKind {
+    Output,
+    /// A marker placed by the user to find a moment in the output again.
+    Mark,
+    /// A message from congregation itself, like a restart notice, which is styled already.
+    Notice,
+    /// Output that's new since the previous run, with `--diff-output`.
+    Added,
+    /// Output of the previous run that's missing from this one, with `--diff-output`.
+    Removed,
+}
+
+/// Severity of a log line, for tasks that prefix their output with one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Finds the level of a line with the given pattern, which is read from its first
+    /// capture group, or from the whole match if it has none.
+    pub fn detect(pattern: &Regex, text: &str) -> Option<Self> {
+        let captures = pattern.captures(text)?;
+        let level = captures.get(1).or(captures.get(0))?;
+        level.as_str().parse().ok()
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(()),
         }
     }
 }
 
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct LogLine {
+    pub text: String,
+    pub stream: Stream,
+    pub kind: LogKind,
+    pub level: Option<LogLevel>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TaskDef {
     pub command: String,
@@ -81,6 +182,33 @@ pub struct TaskDef {
     pub workdir: PathBuf,
     pub color: Option<Color>,
     pub restart_delay_secs: Option<u32>,
+    pub retry_backoff: RetryBackoff,
+    /// Only runs whose output matched this are restarted, whether they succeeded or not.
+    pub retry_on: Option<Regex>,
+    /// How many failed runs within [`CRASHLOOP_WINDOW`] are restarted before giving up.
+    pub crashloop_threshold: Option<usize>,
+    /// Exit codes besides 0 that count as success.
+    pub success_codes: Vec<i32>,
+    pub nice: Option<i32>,
+    pub order: i32,
+    pub watch: Option<GlobSet>,
+    pub group: Option<String>,
+    /// Data written to the task's stdin, which is closed afterwards.
+    pub input: Option<Vec<u8>>,
+    /// The only environment variables passed on to the task, if it doesn't inherit all of them.
+    pub env_passthrough: Option<Vec<String>>,
+    /// Variables set for the task with `-e` and `--env-file`, in the order they were given.
+    pub env: Vec<(String, String)>,
+    /// Whether the command runs in the user's shell with their profile, instead of `sh`.
+    pub login: bool,
+    /// The task counts as ready once this responds successfully.
+    pub health_url: Option<HealthUrl>,
+    /// How long the health URL may take to respond successfully after the task started.
+    pub ready_timeout: Option<Duration>,
+    /// The encoding of the task's output, if it isn't UTF-8.
+    pub encoding: Option<&'static Encoding>,
+    /// How long to wait before the task is first started.
+    pub start_delay: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -88,8 +216,60 @@ pub struct Task {
     pub def: TaskDef,
     pub id: usize,
     pub state: TaskState,
-    pub logs: Vec<String>,
+    pub logs: Vec<LogLine>,
     pub collapsed: bool,
+    pub wrapped: bool,
+    /// Whether the task is asked for colored output, which many tools leave out when their
+    /// output isn't a terminal.
+    pub force_color: bool,
+    /// Whether each run starts with a line showing the command and where it runs.
+    pub echo_command: bool,
+    /// Whether the task starts with an empty environment besides a minimal `PATH`, so it only
+    /// sees the variables given for it.
+    pub clear_env: bool,
+    pub started_at: Option<Instant>,
+    /// Automatic restarts since the task last succeeded or was restarted by hand.
+    pub restart_count: u32,
+    /// When the task's runs failed, as far back as [`CRASHLOOP_WINDOW`].
+    pub recent_failures: Vec<Instant>,
+    /// Whether the task failed too often to be restarted automatically.
+    pub crash_looping: bool,
+    /// Resource usage of the task's processes, collected with `--stats`.
+    pub stats: Option<ProcessStats>,
+    /// Lines printed since the line rate was last checked.
+    pub lines_since_check: usize,
+    /// Lines printed over all runs, for `--counts`.
+    pub line_total: usize,
+    /// How many of `line_total` were printed to stderr.
+    pub stderr_lines: usize,
+    /// Bytes printed over all runs, for `--counts`.
+    pub byte_total: usize,
+    /// Lines of binary output in a row, which are summarized in one notice.
+    pub binary_lines: usize,
+    /// Where in the logs the notice summarizing the current binary lines is.
+    pub binary_notice: usize,
+    /// Lines printed per second, while that's above the `--warn-lines` threshold.
+    pub line_rate_warning: Option<usize>,
+    pub watcher: Option<RecommendedWatcher>,
+    /// Whether the task was stopped because the session's time limit was reached.
+    pub timed_out: bool,
+    /// Whether the running task's processes are suspended.
+    pub paused: bool,
+    /// When a task waiting for its start delay is started.
+    pub start_at: Option<Instant>,
+    /// Whether the health URL responded successfully during the current run.
+    pub ready: bool,
+    /// Whether the health URL didn't respond successfully within the task's ready timeout.
+    pub not_ready: bool,
+    /// Whether the output of the current run matched the `--retry-on` pattern.
+    pub retry_matched: bool,
+    pub health_check: Option<JoinHandle<()>>,
+    /// Where the logs of the current run start.
+    pub run_start: usize,
+    /// Output of the last run that exited on its own, to compare the next one with.
+    pub previous_run: Option<Vec<String>>,
+    /// How often `--diff-output` inserted lines into the logs, which moves the lines after them.
+    pub diff_count: usize,
     pub message_channel: Sender<TaskMessage>,
 }
 
@@ -99,95 +279,242 @@ impl Task {
             def,
             id,
             logs: Vec::new(),
-            state: TaskState::Stopped,
+            state: TaskState::Pending,
             collapsed: false,
+            wrapped: false,
+            force_color: false,
+            echo_command: false,
+            clear_env: false,
+            started_at: None,
+            restart_count: 0,
+            recent_failures: Vec::new(),
+            crash_looping: false,
+            stats: None,
+            lines_since_check: 0,
+            line_total: 0,
+            stderr_lines: 0,
+            byte_total: 0,
+            binary_lines: 0,
+            binary_notice: 0,
+            line_rate_warning: None,
+            watcher: None,
+            timed_out: false,
+            paused: false,
+            start_at: None,
+            ready: false,
+            not_ready: false,
+            retry_matched: false,
+            health_check: None,
+            run_start: 0,
+            previous_run: None,
+            diff_count: 0,
             message_channel,
         }
     }
 
-    pub fn run(&mut self) {
+    /// Runs the task, or waits for its start delay first if it has one.
+    pub fn start(&mut self) -> Result<(), Error> {
+        let Some(delay) = self.def.start_delay else {
+            return self.run();
+        };
+        self.start_at = Some(Instant::now() + delay);
+
+        let id = self.id;
+        let message_channel = self.message_channel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = message_channel
+                .send(TaskMessage {
+                    task: id,
+                    kind: TaskMessageKind::Start,
+                })
+                .await;
+        });
+        Ok(())
+    }
+
+    /// Spawns the task's process. If the shell can't be started, e.g. a `$SHELL` that doesn't
+    /// exist with `--login`, the task fails right away and the error is returned.
+    pub fn run(&mut self) -> Result<(), Error> {
+        self.paused = false;
+        self.start_at = None;
+        self.retry_matched = false;
+        self.crash_looping = false;
+        self.binary_lines = 0;
         let id = self.id;
         let def = self.def.clone();
 
-        if let TaskState::Restarting { .. } | TaskState::ForceRestarting = self.state {
-            self.logs
-                .push("task restarted".dark_grey().italic().to_string());
+        let restart_message = match self.state {
+            TaskState::Restarting { .. } | TaskState::ForceRestarting(RestartReason::Manual) => {
+                Some("task restarted")
+            }
+            TaskState::ForceRestarting(RestartReason::FileChanged) => {
+                Some("task restarted (file changed)")
+            }
+            _ => None,
+        };
+        if let Some(message) = restart_message {
+            self.logs.push(LogLine {
+                text: message.dark_grey().italic().to_string(),
+                stream: Stream::Stdout,
+                kind: LogKind::Notice,
+                level: None,
+            });
+        }
+        if self.echo_command {
+            // scripts from files only show their first line, since notices aren't wrapped
+            let mut lines = def.command.lines();
+            let mut command = lines.next().unwrap_or_default().to_owned();
+            if lines.next().is_some() {
+                command.push_str(" …");
+            }
+            // notices aren't sanitized, so tabs and the like mustn't get into them
+            let text = format!("$ {command} (in {})", def.workdir.display())
+                .replace(|c: char| c.is_control(), " ");
+            self.logs.push(LogLine {
+                text: text.dark_grey().to_string(),
+                stream: Stream::Stdout,
+                kind: LogKind::Notice,
+                level: None,
+            });
         }
+        self.run_start = self.logs.len();
 
-        let mut process = {
+        let mut command = {
             #[cfg(windows)]
             {
                 use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
 
-                Command::new("cmd.exe")
+                let mut command = Command::new("cmd.exe");
+                command
                     .args(["/C", &def.command])
                     .current_dir(def.workdir)
                     .stdin(Stdio::piped())
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped())
-                    .creation_flags(CREATE_NEW_PROCESS_GROUP)
-                    .spawn()
-                    .unwrap()
+                    .creation_flags(CREATE_NEW_PROCESS_GROUP);
+                command
             }
 
             #[cfg(not(windows))]
-            Command::new("sh")
-                .args(["-c", &def.command])
-                .current_dir(def.workdir)
-                .stdin(Stdio::null())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .process_group(0)
-                .spawn()
-                .unwrap()
+            {
+                let (shell, flag) = if def.login {
+                    login_shell()
+                } else {
+                    ("sh".to_owned(), "-c")
+                };
+                let mut command = Command::new(shell);
+                command
+                    .args([flag, &def.command])
+                    .current_dir(def.workdir)
+                    .stdin(if def.input.is_some() {
+                        Stdio::piped()
+                    } else {
+                        Stdio::null()
+                    })
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .process_group(0);
+                command
+            }
         };
-        self.state = TaskState::Running {
-            pid: process.id().unwrap(),
-            stdin: process.stdin.take(),
+        if let Some(keys) = &def.env_passthrough {
+            command.env_clear();
+            for key in keys {
+                if let Some(value) = std::env::var_os(key) {
+                    command.env(key, value);
+                }
+            }
+        }
+        if self.clear_env {
+            command.env_clear();
+            command.env("PATH", minimal_path());
+        }
+        command.envs(def.env.iter().map(|(key, value)| (key, value)));
+        if self.force_color && !self.clear_env {
+            // values the user set themselves are kept
+            for key in ["FORCE_COLOR", "CLICOLOR_FORCE"] {
+                let given = def.env.iter().any(|(name, _)| name == key);
+                if std::env::var_os(key).is_none() && !given {
+                    command.env(key, "1");
+                }
+            }
+        }
+        let program = command
+            .as_std()
+            .get_program()
+            .to_string_lossy()
+            .into_owned();
+        let mut process = match command.spawn() {
+            Ok(process) => process,
+            Err(err) => {
+                // there's no process to wait for, so the task fails right away, with the code
+                // shells use for commands they can't run
+                self.state = TaskState::Exited(TaskExitReason::Failed(127));
+                return Err(Error {
+                    title: format!("error in task '{}'", self.def.name),
+                    message: format!("failed to start '{program}': {err}"),
+                    ..Error::default()
+                });
+            }
         };
+        if let Some(nice) = def.nice {
+            if let Err(err) = set_niceness(&process, nice) {
+                self.logs.push(LogLine {
+                    text: format!("failed to set niceness to {nice}: {err}")
+                        .yellow()
+                        .italic()
+                        .to_string(),
+                    stream: Stream::Stderr,
+                    kind: LogKind::Notice,
+                    level: None,
+                });
+            }
+        }
 
-        {
-            let message_channel = self.message_channel.clone();
-            let stdout = process.stdout.take().unwrap();
+        if let Some(input) = def.input {
+            // written in the background so large inputs don't hold up the event loop,
+            // and dropped afterwards so the task sees the end of its input
+            let mut stdin = process.stdin.take().unwrap();
             tokio::spawn(async move {
-                let mut reader = BufReader::new(stdout);
-                let mut line = String::new();
-
-                while reader.read_line(&mut line).await.unwrap() != 0 {
-                    let _ = message_channel
-                        .send(TaskMessage {
-                            task: id,
-                            kind: TaskMessageKind::Stdout(line.clone()),
-                        })
-                        .await;
-                    line.clear();
-                }
+                use tokio::io::AsyncWriteExt;
+                // the task may exit or close its stdin without reading all of it
+                let _ = stdin.write_all(&input).await;
             });
         }
 
-        {
-            let message_channel = self.message_channel.clone();
-            let stderr = process.stderr.take().unwrap();
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(stderr);
-                let mut line = String::new();
+        self.started_at = Some(Instant::now());
+        self.state = TaskState::Running {
+            pid: process.id().unwrap(),
+            stdin: process.stdin.take(),
+        };
 
-                while reader.read_line(&mut line).await.unwrap() != 0 {
-                    let _ = message_channel
-                        .send(TaskMessage {
-                            task: id,
-                            kind: TaskMessageKind::Stdout(line.clone()),
-                        })
-                        .await;
-                    line.clear();
-                }
-            });
+        self.ready = false;
+        self.not_ready = false;
+        self.stop_health_check();
+        if let Some(url) = def.health_url {
+            self.health_check = Some(spawn_health_check(
+                url,
+                def.ready_timeout,
+                id,
+                self.message_channel.clone(),
+            ));
         }
 
+        let readers = futures::future::join(
+            self.spawn_reader(Stream::Stdout, process.stdout.take().unwrap()),
+            self.spawn_reader(Stream::Stderr, process.stderr.take().unwrap()),
+        );
+
         {
             let message_channel = self.message_channel.clone();
             tokio::spawn(async move {
                 let status = process.wait().await.unwrap();
+
+                // let the remaining output arrive before the exit. processes left running
+                // in the background may keep the pipes open, so don't wait for too long
+                let _ = tokio::time::timeout(Duration::from_secs(1), readers).await;
+
                 let _ = message_channel
                     .send(TaskMessage {
                         task: id,
@@ -200,9 +527,13 @@ impl Task {
                                 use nix::sys::signal::Signal;
                                 use std::os::unix::process::ExitStatusExt;
 
-                                TaskExitReason::Killed(
-                                    Signal::try_from(status.signal().unwrap()).unwrap().as_str(),
-                                )
+                                let signal = status.signal().unwrap();
+                                TaskExitReason::Killed(match Signal::try_from(signal) {
+                                    Ok(signal) => signal.as_str(),
+                                    // e.g. real-time signals, which have no names. there are
+                                    // only a few of them, so leaking their names is fine
+                                    Err(_) => format!("signal {signal}").leak(),
+                                })
                             }
 
                             #[cfg(not(unix))]
@@ -212,42 +543,272 @@ impl Task {
                     .await;
             });
         }
+        Ok(())
+    }
+
+    fn spawn_reader(
+        &self,
+        stream: Stream,
+        output: impl AsyncRead + Unpin + Send + 'static,
+    ) -> JoinHandle<()> {
+        let id = self.id;
+        let message_channel = self.message_channel.clone();
+        let encoding = self.def.encoding;
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(output);
+            let mut line = Vec::new();
+
+            while reader.read_until(b'\n', &mut line).await.unwrap() != 0 {
+                // invalid bytes become replacement characters instead of ending the output
+                let text = match encoding {
+                    Some(encoding) => encoding.decode_without_bom_handling(&line).0,
+                    None => String::from_utf8_lossy(&line),
+                };
+                let _ = message_channel
+                    .send(TaskMessage {
+                        task: id,
+                        kind: TaskMessageKind::Output(stream, text.into_owned()),
+                    })
+                    .await;
+                line.clear();
+            }
+        })
+    }
+
+    /// Updates the line rate warning from the lines printed since the last check, which happened
+    /// `elapsed` ago.
+    pub fn check_line_rate(&mut self, threshold: usize, elapsed: Duration) {
+        let rate = (self.lines_since_check as f64 / elapsed.as_secs_f64()).round() as usize;
+        self.line_rate_warning = (rate > threshold).then_some(rate);
+        self.lines_since_check = 0;
+    }
+
+    /// Marks which lines of the run that just exited are new or missing compared to the previous
+    /// run, and keeps its output to compare the next run with.
+    pub fn diff_with_previous_run(&mut self) {
+        let output: Vec<String> = self.logs[self.run_start..]
+            .iter()
+            .filter(|log| log.kind == LogKind::Output)
+            .map(|log| log.text.clone())
+            .collect();
+        let Some(previous) = self.previous_run.replace(output.clone()) else {
+            return;
+        };
+        if previous.len() > MAX_DIFF_LINES || output.len() > MAX_DIFF_LINES {
+            return;
+        }
+
+        // lines of the previous run that go in front of each line of this one
+        let mut removed = vec![Vec::new(); output.len() + 1];
+        let mut added = vec![false; output.len()];
+        for op in similar::capture_diff_slices(similar::Algorithm::Myers, &previous, &output) {
+            if op.tag() == similar::DiffTag::Equal {
+                continue;
+            }
+            removed[op.new_range().start].extend_from_slice(&previous[op.old_range()]);
+            added[op.new_range()].fill(true);
+        }
+
+        let removed_line = |text: String, stream| LogLine {
+            text,
+            stream,
+            kind: LogKind::Removed,
+            level: None,
+        };
+        self.diff_count += 1;
+        let run_logs = self.logs.split_off(self.run_start);
+        let mut output_index = 0;
+        for mut log in run_logs {
+            if log.kind == LogKind::Output {
+                let lines = std::mem::take(&mut removed[output_index]);
+                self.logs
+                    .extend(lines.into_iter().map(|text| removed_line(text, log.stream)));
+                if added[output_index] {
+                    log.kind = LogKind::Added;
+                }
+                output_index += 1;
+            }
+            self.logs.push(log);
+        }
+        let lines = std::mem::take(&mut removed[output_index]);
+        self.logs.extend(
+            lines
+                .into_iter()
+                .map(|text| removed_line(text, Stream::Stdout)),
+        );
+    }
+
+    /// Stops polling the health URL, for example because the run it belongs to ended.
+    pub fn stop_health_check(&mut self) {
+        if let Some(health_check) = self.health_check.take() {
+            health_check.abort();
+        }
+    }
+
+    /// Whether the task is done for good. Exited tasks that watch files may still be restarted.
+    pub fn is_finished(&self) -> bool {
+        match self.state {
+            TaskState::Stopped => true,
+            TaskState::Exited(_) => self.watcher.is_none(),
+            _ => false,
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.state,
+            TaskState::Exited(TaskExitReason::Failed(_) | TaskExitReason::Killed(_))
+        )
+    }
+
+    /// Starts watching the working directory for changes to files matching the task's `--watch`
+    /// globs, which are reported as [`TaskMessageKind::FileChanged`] once changes settle down.
+    pub fn start_watching(&mut self) -> Result<(), Error> {
+        let Some(globs) = self.def.watch.clone() else {
+            return Ok(());
+        };
+
+        let workdir = self.def.workdir.clone();
+        let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+
+            let matches = event.paths.iter().any(|path| {
+                path.strip_prefix(&workdir)
+                    .is_ok_and(|path| globs.is_match(path))
+            });
+            if matches {
+                let _ = changes_tx.send(());
+            }
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(&self.def.workdir, RecursiveMode::Recursive)?;
+            Ok(watcher)
+        })
+        .map_err(|err| Error {
+            title: format!("error in task '{}'", self.def.name),
+            message: format!("failed to watch files: {err}"),
+            ..Error::default()
+        })?;
+        self.watcher = Some(watcher);
+
+        let id = self.id;
+        let message_channel = self.message_channel.clone();
+        tokio::spawn(async move {
+            // the loop ends once the watcher, and with it the sender, is dropped
+            while changes_rx.recv().await.is_some() {
+                while let Ok(Some(())) =
+                    tokio::time::timeout(WATCH_DEBOUNCE, changes_rx.recv()).await
+                {}
+
+                let _ = message_channel
+                    .send(TaskMessage {
+                        task: id,
+                        kind: TaskMessageKind::FileChanged,
+                    })
+                    .await;
+            }
+        });
+
+        Ok(())
     }
 
     pub fn end_gracefully(&mut self) {
-        let state = std::mem::replace(&mut self.state, TaskState::Stopped);
-        match state {
-            TaskState::Running { pid, stdin } => send_stop_signal(pid, stdin),
+        self.watcher = None;
+
+        let state = std::mem::replace(&mut self.state, TaskState::Stopping);
+        self.state = match state {
+            TaskState::Running { pid, stdin } => {
+                send_stop_signal(pid, stdin);
+                self.continue_paused(pid);
+                TaskState::Stopping
+            }
             TaskState::Restarting { cancel_tx, .. } => {
-                cancel_tx.send(()).unwrap();
-                let _ = self.message_channel.try_send(TaskMessage {
-                    task: self.id,
-                    // the receiver will correctly mark this task as completed,
-                    // because the state is set to Stopped. this is kind of ugly,
-                    // but it works for now and avoids the issue of completed_task_count
-                    // not incrementing when a restarting task is stopped.
-                    kind: TaskMessageKind::Exited(TaskExitReason::Succeeded),
-                });
+                let _ = cancel_tx.send(());
+                TaskState::Stopped
             }
-            TaskState::Exited(reason) => self.state = TaskState::Exited(reason),
-            _ => {}
+            TaskState::ForceRestarting(_) | TaskState::Stopping => TaskState::Stopping,
+            TaskState::Pending => TaskState::Stopped,
+            state => state,
         };
     }
 
-    pub fn force_restart(&mut self) {
-        let state = std::mem::replace(&mut self.state, TaskState::ForceRestarting);
+    pub fn force_restart(&mut self, reason: RestartReason) -> Result<(), Error> {
+        self.restart_count = 0;
+        self.recent_failures.clear();
+        let state = std::mem::replace(&mut self.state, TaskState::ForceRestarting(reason));
         match state {
-            TaskState::Running { pid, stdin } => send_stop_signal(pid, stdin),
+            TaskState::Running { pid, stdin } => {
+                send_stop_signal(pid, stdin);
+                self.continue_paused(pid);
+            }
             TaskState::Restarting { cancel_tx, .. } => {
-                cancel_tx.send(()).unwrap();
-                self.run();
+                let _ = cancel_tx.send(());
+                return self.run();
             }
-            TaskState::ForceRestarting => {}
-            _ => self.run(),
+            TaskState::ForceRestarting(_) | TaskState::Stopping => self.state = state,
+            _ => return self.run(),
         }
+        Ok(())
+    }
+
+    /// Suspends the processes of the running task, without ending them.
+    pub fn pause(&mut self) -> Result<(), String> {
+        if let TaskState::Running { pid, .. } = self.state {
+            send_pause_signal(pid, true)?;
+            self.paused = true;
+        }
+        Ok(())
+    }
+
+    /// Continues the processes of a paused task.
+    pub fn resume(&mut self) -> Result<(), String> {
+        if let TaskState::Running { pid, .. } = self.state {
+            send_pause_signal(pid, false)?;
+            self.paused = false;
+        }
+        Ok(())
+    }
+
+    /// Continues a paused task that is being ended, since suspended processes only handle the
+    /// signal to stop once they continue.
+    fn continue_paused(&mut self, pid: u32) {
+        if self.paused {
+            let _ = send_pause_signal(pid, false);
+            self.paused = false;
+        }
+    }
+
+    /// Remembers that a run failed, and tells whether the task failed more often within
+    /// [`CRASHLOOP_WINDOW`] than its `--crashloop-threshold` allows.
+    pub fn record_failure(&mut self) -> bool {
+        let Some(threshold) = self.def.crashloop_threshold else {
+            return false;
+        };
+        let now = Instant::now();
+        self.recent_failures
+            .retain(|at| now.duration_since(*at) < CRASHLOOP_WINDOW);
+        self.recent_failures.push(now);
+        self.recent_failures.len() > threshold
     }
 
     pub fn start_restart_countdown(&mut self, exit_reason: TaskExitReason, delay: u32) {
+        // runs retried because of their output failed, even if the exit code says otherwise
+        if exit_reason == TaskExitReason::Succeeded && !self.retry_matched {
+            self.restart_count = 0;
+        }
+        let delay = match self.def.retry_backoff {
+            RetryBackoff::Fixed => delay,
+            RetryBackoff::Exponential => delay
+                .max(1)
+                .saturating_mul(2u32.saturating_pow(self.restart_count))
+                .min(MAX_RESTART_DELAY_SECS),
+        };
+        self.restart_count += 1;
+
         let id = self.id;
         let message_channel = self.message_channel.clone();
 
@@ -281,6 +842,90 @@ impl Task {
     }
 }
 
+/// Lowers or raises the scheduling priority of a task's process group. Windows has no
+/// niceness, so it gets the closest priority class instead.
+fn set_niceness(process: &Child, nice: i32) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Threading::{
+            SetPriorityClass, ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS,
+            HIGH_PRIORITY_CLASS, IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+        };
+
+        let class = match nice {
+            10.. => IDLE_PRIORITY_CLASS,
+            1..=9 => BELOW_NORMAL_PRIORITY_CLASS,
+            0 => NORMAL_PRIORITY_CLASS,
+            -9..=-1 => ABOVE_NORMAL_PRIORITY_CLASS,
+            _ => HIGH_PRIORITY_CLASS,
+        };
+        let handle = process.raw_handle().ok_or("the task already exited")?;
+        if unsafe { SetPriorityClass(handle, class) } == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    {
+        use nix::{errno::Errno, libc};
+
+        // the task runs in its own process group, whose id is the pid of the shell
+        let pgid = process.id().ok_or("the task already exited")?;
+        let result = unsafe { libc::setpriority(libc::PRIO_PGRP, pgid, nice) };
+        Errno::result(result)
+            .map(drop)
+            .map_err(|err| err.desc().to_owned())
+    }
+}
+
+/// The `PATH` of tasks with an empty environment, which only finds the system's basic commands.
+fn minimal_path() -> String {
+    if cfg!(windows) {
+        let root = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_owned());
+        format!(r"{root}\System32;{root}")
+    } else {
+        "/usr/bin:/bin".to_owned()
+    }
+}
+
+/// The user's shell and the flag to run a command in it with the same setup as in a terminal.
+#[cfg(not(windows))]
+fn login_shell() -> (String, &'static str) {
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".to_owned());
+    // zsh only reads .zshrc, where most people set up their PATH, in interactive shells
+    let flag = if shell.ends_with("zsh") { "-ic" } else { "-lc" };
+    (shell, flag)
+}
+
+/// Suspends or continues a task's process group. Windows has no equivalent of these signals.
+#[allow(unused_variables)]
+fn send_pause_signal(pid: u32, pause: bool) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        Err("pausing tasks isn't supported on Windows".into())
+    }
+
+    #[cfg(unix)]
+    {
+        use nix::{
+            sys::signal::{self, Signal},
+            unistd::Pid,
+        };
+
+        // the task runs in its own process group, whose id is the pid of the shell
+        let signal = if pause {
+            Signal::SIGSTOP
+        } else {
+            Signal::SIGCONT
+        };
+        signal::kill(Pid::from_raw(-(pid as i32)), signal).map_err(|err| err.desc().to_owned())
+    }
+}
+
 fn send_stop_signal(pid: u32, #[allow(unused_variables)] stdin: Option<ChildStdin>) {
     #[cfg(windows)]
     {
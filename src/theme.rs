@@ -0,0 +1,291 @@
+use crate::task::{RestartReason, Task, TaskExitReason, TaskState};
+use crossterm::style::{StyledContent, Stylize};
+use std::time::Instant;
+
+/// The words and glyphs used to show task states, which can be customized with `--status-text`.
+///
+/// `{code}`, `{signal}` and `{secs}` are replaced with the exit code, the signal that killed the
+/// task and the seconds until it restarts or it had to become ready respectively.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub pending: String,
+    pub waiting: String,
+    pub running: String,
+    pub starting: String,
+    pub ready: String,
+    pub not_ready: String,
+    pub stopping: String,
+    pub stopped: String,
+    pub paused: String,
+    pub timed_out: String,
+    pub completed: String,
+    pub failed: String,
+    pub killed: String,
+    pub restarting: String,
+    pub restarting_in: String,
+    pub file_changed: String,
+    pub crash_looping: String,
+    pub success_glyph: String,
+    pub failure_glyph: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            pending: "pending".into(),
+            waiting: "waiting ({secs}s)".into(),
+            running: "running...".into(),
+            starting: "starting...".into(),
+            ready: "ready".into(),
+            not_ready: "not ready after {secs}s".into(),
+            stopping: "stopping...".into(),
+            stopped: "stopped".into(),
+            paused: "paused".into(),
+            timed_out: "timed out".into(),
+            completed: "completed".into(),
+            failed: "failed (code {code})".into(),
+            killed: "killed ({signal})".into(),
+            restarting: "restarting...".into(),
+            restarting_in: "restarting in {secs}s...".into(),
+            file_changed: "restarting (file changed)...".into(),
+            crash_looping: "crash-looping".into(),
+            success_glyph: "✓".into(),
+            failure_glyph: "✗".into(),
+        }
+    }
+}
+
+impl Theme {
+    pub const KEYS: &[&str] = &[
+        "pending",
+        "waiting",
+        "running",
+        "starting",
+        "ready",
+        "not-ready",
+        "stopping",
+        "stopped",
+        "paused",
+        "timed-out",
+        "completed",
+        "failed",
+        "killed",
+        "restarting",
+        "restarting-in",
+        "file-changed",
+        "crash-looping",
+        "success-glyph",
+        "failure-glyph",
+    ];
+
+    /// Sets the text for a key from [`Theme::KEYS`], returning false if the key is unknown.
+    pub fn set(&mut self, key: &str, text: String) -> bool {
+        let field = match key {
+            "pending" => &mut self.pending,
+            "waiting" => &mut self.waiting,
+            "running" => &mut self.running,
+            "starting" => &mut self.starting,
+            "ready" => &mut self.ready,
+            "not-ready" => &mut self.not_ready,
+            "stopping" => &mut self.stopping,
+            "stopped" => &mut self.stopped,
+            "paused" => &mut self.paused,
+            "timed-out" => &mut self.timed_out,
+            "completed" => &mut self.completed,
+            "failed" => &mut self.failed,
+            "killed" => &mut self.killed,
+            "restarting" => &mut self.restarting,
+            "restarting-in" => &mut self.restarting_in,
+            "file-changed" => &mut self.file_changed,
+            "crash-looping" => &mut self.crash_looping,
+            "success-glyph" => &mut self.success_glyph,
+            "failure-glyph" => &mut self.failure_glyph,
+            _ => return false,
+        };
+        *field = text;
+        true
+    }
+
+    pub fn exit_reason(&self, reason: &TaskExitReason) -> StyledContent<String> {
+        match reason {
+            TaskExitReason::Succeeded => self.completed.clone().green(),
+            TaskExitReason::Killed(signal) => self.killed.replace("{signal}", signal).red(),
+            TaskExitReason::Failed(code) => self.failed.replace("{code}", &code.to_string()).red(),
+        }
+    }
+
+    pub fn exit_glyph(&self, reason: &TaskExitReason) -> StyledContent<String> {
+        match reason {
+            TaskExitReason::Succeeded => self.success_glyph.clone().green(),
+            TaskExitReason::Killed(signal) => format!("{} ({signal})", self.failure_glyph).red(),
+            TaskExitReason::Failed(code) => format!("{} ({code})", self.failure_glyph).red(),
+        }
+    }
+
+    /// The state of a running task with a health URL, which is starting until the URL responds,
+    /// or not ready if it didn't within the task's ready timeout.
+    pub fn readiness(&self, task: &Task) -> StyledContent<String> {
+        if task.ready {
+            self.ready.clone().green()
+        } else if task.not_ready {
+            let secs = task.def.ready_timeout.unwrap_or_default().as_secs();
+            self.not_ready.replace("{secs}", &secs.to_string()).red()
+        } else {
+            self.starting.clone().yellow()
+        }
+    }
+
+    /// The state of a task, noting when it's waiting for its start delay, was paused, or was
+    /// stopped by the session's time limit.
+    pub fn task_state(&self, task: &Task) -> StyledContent<String> {
+        match task.state {
+            TaskState::Pending if task.start_at.is_some() => {
+                let remaining = task
+                    .start_at
+                    .unwrap()
+                    .saturating_duration_since(Instant::now());
+                // rounded up, so it doesn't show 0s before the task starts
+                let secs = remaining.as_millis().div_ceil(1000);
+                self.waiting
+                    .replace("{secs}", &secs.to_string())
+                    .dark_grey()
+            }
+            TaskState::Running { .. } if task.paused => self.paused.clone().yellow(),
+            TaskState::Stopping | TaskState::Stopped if task.timed_out => {
+                self.timed_out.clone().red()
+            }
+            TaskState::Exited(reason) if task.crash_looping => format!(
+                "{}: {}",
+                self.exit_reason(&reason).content(),
+                self.crash_looping
+            )
+            .red(),
+            _ => self.state(&task.state),
+        }
+    }
+
+    pub fn state(&self, state: &TaskState) -> StyledContent<String> {
+        match state {
+            TaskState::Pending => self.pending.clone().dark_grey(),
+            TaskState::Running { .. } => self.running.clone().green(),
+            TaskState::Stopping => self.stopping.clone().yellow(),
+            TaskState::Stopped => self.stopped.clone().green(),
+            TaskState::Exited(reason) => self.exit_reason(reason),
+            TaskState::Restarting {
+                exit_reason,
+                remaining_secs,
+                ..
+            } => format!(
+                "{}: {}",
+                self.exit_reason(exit_reason).content(),
+                self.restarting_in
+                    .replace("{secs}", &remaining_secs.to_string())
+            )
+            .yellow(),
+            TaskState::ForceRestarting(RestartReason::Manual) => self.restarting.clone().yellow(),
+            TaskState::ForceRestarting(RestartReason::FileChanged) => {
+                self.file_changed.clone().yellow()
+            }
+        }
+    }
+}
+
+/// A color scheme for the interactive view, which can be cycled through with `t`.
+///
+/// The view is drawn with the default colors, and the other palettes change them afterwards, so
+/// colors in task output are adjusted the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+    Default,
+    /// Bright colors only, for terminals where dark ones are hard to read.
+    HighContrast,
+    /// No colors at all, keeping bold and other attributes.
+    Monochrome,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Default, Palette::HighContrast, Palette::Monochrome];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::HighContrast => "high contrast",
+            Palette::Monochrome => "monochrome",
+        }
+    }
+
+    /// Changes the colors set by the escape sequences in a drawn row to the palette's.
+    pub fn recolor(self, row: Vec<u8>) -> Vec<u8> {
+        if self == Palette::Default {
+            return row;
+        }
+        let row = String::from_utf8_lossy(&row);
+
+        let mut result = String::new();
+        let mut rest = row.as_ref();
+        while let Some(start) = rest.find("\x1b[") {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            // only styles are changed, any other sequence is kept as it is
+            let sgr = rest[2..]
+                .find(|c: char| !c.is_ascii_digit() && c != ';')
+                .filter(|&end| rest[2 + end..].starts_with('m'));
+            let Some(end) = sgr else {
+                result.push_str("\x1b[");
+                rest = &rest[2..];
+                continue;
+            };
+
+            let params = self.recolor_params(&rest[2..2 + end]);
+            // an empty sequence would reset everything, so it's left out instead
+            if !params.is_empty() {
+                result.push_str(&format!("\x1b[{params}m"));
+            }
+            rest = &rest[2 + end + 1..];
+        }
+        result.push_str(rest);
+        result.into_bytes()
+    }
+
+    /// Changes the color parameters of a style sequence, e.g. `1;38;5;8`.
+    fn recolor_params(self, params: &str) -> String {
+        // a bare `ESC[m` resets everything
+        if params.is_empty() {
+            return "0".into();
+        }
+
+        let mut params = params.split(';');
+        let mut result = Vec::new();
+        while let Some(param) = params.next() {
+            match param.parse::<u8>() {
+                // extended colors: `38;5;<index>` or `38;2;<r>;<g>;<b>`
+                Ok(kind @ (38 | 48 | 58)) => {
+                    let color: Vec<&str> = match params.next() {
+                        Some("5") => params.next().into_iter().collect(),
+                        Some("2") => params.by_ref().take(3).collect(),
+                        _ => continue,
+                    };
+                    match (self, color.as_slice()) {
+                        (Palette::Monochrome, _) => {}
+                        (Palette::HighContrast, [index]) => {
+                            // dark grey becomes light grey, dark colors their bright versions
+                            let index = match index.parse::<u8>() {
+                                Ok(8) => 7,
+                                Ok(index @ 1..=6) => index + 8,
+                                Ok(index) => index,
+                                Err(_) => continue,
+                            };
+                            result.push(format!("{kind};5;{index}"));
+                        }
+                        _ => result.push(format!("{kind};2;{}", color.join(";"))),
+                    }
+                }
+                Ok(30..=37 | 39 | 40..=47 | 49 | 90..=97 | 100..=107 | 59)
+                    if self == Palette::Monochrome => {}
+                Ok(code @ (31..=36 | 41..=46)) => result.push((code + 60).to_string()),
+                _ => result.push(param.to_owned()),
+            }
+        }
+        result.join(";")
+    }
+}